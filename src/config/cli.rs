@@ -1,3 +1,4 @@
+use crate::config::output::{marker, Status};
 use crate::config::{validate_config, ConfigLoader, ToolConfig};
 use crate::tool_metadata::TOOL_METADATA;
 use anyhow::{Context, Result};
@@ -13,19 +14,34 @@ pub enum ConfigCommand {
         #[arg(long, default_value = "yaml")]
         format: OutputFormat,
 
+        /// Shorthand for `--format json`
+        #[arg(long)]
+        json: bool,
+
         /// Show configuration for specific repository
         #[arg(long)]
         repo: Option<PathBuf>,
     },
 
-    /// Validate a configuration file
+    /// Validate a configuration file, or with --editor the embedding key
+    /// stored in an editor config
     Validate {
         /// Path to config file to validate
-        path: PathBuf,
+        #[arg(required_unless_present = "editor")]
+        path: Option<PathBuf>,
 
         /// Show verbose validation errors
         #[arg(short, long)]
         verbose: bool,
+
+        /// Check the key stored in this editor's config against the provider
+        /// instead (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long, conflicts_with = "path")]
+        editor: Option<String>,
+
+        /// Name of the server entry whose key to check
+        #[arg(long, default_value = "narsil-mcp", requires = "editor")]
+        server_name: String,
     },
 
     /// Initialize a new configuration file
@@ -45,6 +61,9 @@ pub enum ConfigCommand {
         /// Run the neural API key setup wizard
         #[arg(long)]
         neural: bool,
+
+        #[command(flatten)]
+        wizard: Box<NeuralArgs>,
     },
 
     /// Apply a preset to configuration
@@ -57,6 +76,94 @@ pub enum ConfigCommand {
         project: bool,
     },
 
+    /// Remove a stored embedding API key from an editor config
+    RemoveKey {
+        /// Editor whose config to edit (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: String,
+
+        /// Provider whose key to remove (voyage, openai, custom, cohere, gemini, azure, ollama, onnx, openai-compatible)
+        #[arg(long)]
+        provider: String,
+
+        /// Name of the server entry to edit
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+    },
+
+    /// Remove the narsil-mcp server entry from an editor config
+    Uninstall {
+        /// Editor whose config to edit (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: String,
+
+        /// Name of the server entry to remove
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+    },
+
+    /// Show where an editor keeps its config, without changing anything
+    Path {
+        /// Editor to look up (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: String,
+    },
+
+    /// List narsil-mcp servers configured in detected editors
+    List {
+        /// Name of the server entry to look for
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+
+        /// Print the servers as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check detected editor configs for problems with the narsil-mcp entry
+    Doctor {
+        /// Name of the server entry to check
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+
+        /// Print the reports as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Estimate the tokens and cost of embedding repositories with a provider
+    Estimate {
+        /// Repositories to index, comma or space separated (default: the current directory)
+        #[arg(long)]
+        repos: Option<String>,
+
+        /// Embedding provider (voyage, openai, custom, cohere, gemini, azure, ollama, onnx, openai-compatible)
+        #[arg(long, default_value = "voyage")]
+        provider: String,
+
+        /// Embedding model (default: the provider's recommended model)
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Price in US dollars per 1,000 tokens, for models without a known price
+        #[arg(long)]
+        price_per_1k: Option<f64>,
+    },
+
+    /// Turn neural embeddings on or off in an editor config, keeping the API key
+    Neural {
+        /// Whether to add or remove the --neural flag
+        state: Toggle,
+
+        /// Editor whose config to edit (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: String,
+
+        /// Name of the server entry to edit
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+    },
+
     /// Export the current effective configuration
     Export {
         /// Include resolved preset settings
@@ -66,7 +173,139 @@ pub enum ConfigCommand {
         /// Output format (yaml or json)
         #[arg(long, default_value = "yaml")]
         format: OutputFormat,
+
+        /// Export this editor's narsil-mcp server entry instead, with secrets
+        /// redacted (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: Option<String>,
+
+        /// Name of the server entry to export
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+
+        /// Write the server entry to this file: TOML if it ends in .toml,
+        /// else JSON (default: JSON on stdout)
+        #[arg(long, requires = "editor")]
+        output: Option<PathBuf>,
     },
+
+    /// Merge a server entry written by `config export --editor` into an editor config
+    Import {
+        /// Exported server entry (JSON or TOML)
+        file: PathBuf,
+
+        /// Editor whose config to edit (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+        #[arg(long)]
+        editor: String,
+
+        /// Name of the server entry to write
+        #[arg(long, default_value = "narsil-mcp")]
+        server_name: String,
+
+        /// Print a diff of the editor config changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Options for running the neural wizard without prompts
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct NeuralArgs {
+    /// Embedding provider (voyage, openai, custom, cohere, gemini, azure, ollama, onnx,
+    /// openai-compatible). Setting this runs the wizard non-interactively.
+    #[arg(long, requires = "neural")]
+    pub provider: Option<String>,
+
+    /// API key to store (for ollama: the server address; for onnx: the
+    /// local model directory). If omitted and
+    /// stdin is piped, the key is read from stdin.
+    #[arg(long, requires = "provider")]
+    pub api_key: Option<String>,
+
+    /// Separate key for reranking, for providers that sell it on its own
+    /// (voyage, cohere)
+    #[arg(long, requires = "provider")]
+    pub rerank_key: Option<String>,
+
+    /// Editor to configure (claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim)
+    #[arg(long, requires = "provider")]
+    pub editor: Option<String>,
+
+    /// Editor config file to edit, e.g. one of several VS Code configs or a
+    /// file in a custom location (also set by NARSIL_CONFIG_PATH). Without
+    /// --editor the editor is inferred from the path and contents.
+    #[arg(long, requires = "neural", conflicts_with = "env_file")]
+    pub config_path: Option<PathBuf>,
+
+    /// Windows home whose editor configs to use when running inside WSL, e.g.
+    /// `C:\Users\me` (also set by NARSIL_WINDOWS_HOME; default: detected)
+    #[arg(long, requires = "neural")]
+    pub windows_home: Option<PathBuf>,
+
+    /// Skip the test request that checks the key
+    #[arg(long, requires = "provider")]
+    pub no_validate: bool,
+
+    /// Seconds to wait for the test request that checks the key (default: 15)
+    #[arg(long, requires = "neural")]
+    pub validate_timeout: Option<u64>,
+
+    /// Seconds the server waits for each embedding API request, written as
+    /// EMBEDDING_TIMEOUT (default: the server's own, 120)
+    #[arg(long, requires = "provider")]
+    pub request_timeout: Option<u64>,
+
+    /// Overwrite a key that is already set in the editor config
+    #[arg(long, requires = "provider")]
+    pub force: bool,
+
+    /// Print a diff of the editor config changes without writing them
+    #[arg(long, requires = "neural")]
+    pub dry_run: bool,
+
+    /// Repositories to index, comma or space separated (default: the current directory)
+    #[arg(long, requires = "provider")]
+    pub repos: Option<String>,
+
+    /// Write the variables to this .env file instead of an editor config
+    #[arg(long, requires = "neural")]
+    pub env_file: Option<PathBuf>,
+
+    /// Name of the server entry to write, to run several instances side by side
+    #[arg(long, requires = "neural")]
+    pub server_name: Option<String>,
+
+    /// Command the editor runs to start the server, e.g. an absolute path
+    /// when narsil-mcp isn't on the editor's PATH (default: narsil-mcp)
+    #[arg(long, requires = "neural")]
+    pub command: Option<String>,
+
+    /// Reconcile the args of an existing server entry: add `--neural` and
+    /// missing repos, keeping any other flags already there
+    #[arg(long, requires = "neural")]
+    pub merge_args: bool,
+
+    /// Store the API key in the OS keyring and write only a reference to it
+    #[arg(long, requires = "neural")]
+    pub keyring: bool,
+
+    /// Don't warn about repositories that are empty or hold no source files
+    #[arg(long, requires = "neural")]
+    pub no_repo_check: bool,
+
+    /// Print ASCII status markers instead of emoji (also set by NO_COLOR)
+    #[arg(long, requires = "neural")]
+    pub no_color: bool,
+
+    /// Finish by printing what was written (editor, config path, provider,
+    /// model and masked key) as JSON
+    #[arg(long, requires = "neural")]
+    pub json: bool,
+
+    /// Log each step of writing the editor config to stderr, with secrets
+    /// masked (RUST_LOG=narsil_mcp=debug does the same)
+    #[arg(long, requires = "neural")]
+    pub verbose: bool,
 }
 
 /// Tools CLI subcommands
@@ -104,6 +343,12 @@ pub enum ToolsCommand {
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Toggle {
+    On,
+    Off,
+}
+
 #[derive(Debug, Clone, PartialEq, clap::ValueEnum)]
 pub enum OutputFormat {
     Yaml,
@@ -112,18 +357,75 @@ pub enum OutputFormat {
 }
 
 /// Handle config subcommands
+///
+/// API keys are masked in any error returned, since wizard failures can
+/// carry them in their context.
 pub async fn handle_config_command(cmd: ConfigCommand) -> Result<()> {
+    run_config_command(cmd)
+        .await
+        .map_err(|e| anyhow::anyhow!(crate::config::wizard::redact_keys(&format!("{:#}", e))))
+}
+
+async fn run_config_command(cmd: ConfigCommand) -> Result<()> {
     match cmd {
-        ConfigCommand::Show { format, repo } => cmd_show(format, repo),
-        ConfigCommand::Validate { path, verbose } => cmd_validate(path, verbose),
+        ConfigCommand::Show {
+            json: true, repo, ..
+        } => cmd_show(OutputFormat::Json, repo),
+        ConfigCommand::Show { format, repo, .. } => cmd_show(format, repo),
+        ConfigCommand::Validate {
+            editor: Some(editor),
+            server_name,
+            ..
+        } => cmd_validate_key(&editor, server_name).await,
+        ConfigCommand::Validate { path, verbose, .. } => {
+            cmd_validate(path.unwrap_or_default(), verbose)
+        }
         ConfigCommand::Init {
             preset,
             project,
             user,
             neural,
-        } => cmd_init(preset, project, user, neural).await,
+            wizard,
+        } => cmd_init(preset, project, user, neural, *wizard).await,
         ConfigCommand::Preset { preset, project } => cmd_preset(preset, project),
-        ConfigCommand::Export { resolved, format } => cmd_export(resolved, format),
+        ConfigCommand::RemoveKey {
+            editor,
+            provider,
+            server_name,
+        } => cmd_remove_key(editor, provider, server_name).await,
+        ConfigCommand::Uninstall {
+            editor,
+            server_name,
+        } => cmd_uninstall(editor, server_name).await,
+        ConfigCommand::Path { editor } => cmd_path(&editor),
+        ConfigCommand::List { server_name, json } => cmd_list(&server_name, json),
+        ConfigCommand::Doctor { server_name, json } => cmd_doctor(&server_name, json),
+        ConfigCommand::Estimate {
+            repos,
+            provider,
+            model,
+            price_per_1k,
+        } => cmd_estimate(repos, &provider, model, price_per_1k),
+        ConfigCommand::Neural {
+            state,
+            editor,
+            server_name,
+        } => cmd_neural(state, editor, server_name).await,
+        ConfigCommand::Export {
+            editor: Some(editor),
+            server_name,
+            output,
+            ..
+        } => cmd_export_server(editor, server_name, output),
+        ConfigCommand::Export {
+            resolved, format, ..
+        } => cmd_export(resolved, format),
+        ConfigCommand::Import {
+            file,
+            editor,
+            server_name,
+            dry_run,
+        } => cmd_import(file, editor, server_name, dry_run).await,
     }
 }
 
@@ -208,7 +510,7 @@ fn cmd_validate(path: PathBuf, verbose: bool) -> Result<()> {
     // Validate the config
     match validate_config(&config) {
         Ok(_) => {
-            println!("✓ Configuration is valid: {:?}", path);
+            println!("{} Configuration is valid: {:?}", marker(Status::Ok), path);
             if verbose {
                 println!("\nConfiguration summary:");
                 println!("  Version: {}", config.version);
@@ -218,7 +520,11 @@ fn cmd_validate(path: PathBuf, verbose: bool) -> Result<()> {
             Ok(())
         }
         Err(e) => {
-            eprintln!("✗ Configuration validation failed: {:?}", path);
+            eprintln!(
+                "{} Configuration validation failed: {:?}",
+                marker(Status::Fail),
+                path
+            );
             if verbose {
                 eprintln!("\nError details:");
                 eprintln!("  {:#}", e);
@@ -231,12 +537,102 @@ fn cmd_validate(path: PathBuf, verbose: bool) -> Result<()> {
     }
 }
 
-async fn cmd_init(preset: Option<String>, project: bool, user: bool, neural: bool) -> Result<()> {
+/// Check the embedding key stored in an editor config still works
+async fn cmd_validate_key(editor: &str, server_name: String) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::NeuralWizard;
+
+    let config_path = get_editor_config_path(parse_editor_arg(editor)?);
+    let provider = NeuralWizard::new()
+        .with_server_name(server_name)
+        .validate_stored_key(&config_path)
+        .await?;
+
+    println!(
+        "{} {} key in {} is valid",
+        marker(Status::Ok),
+        provider.display_name(),
+        config_path.display()
+    );
+    Ok(())
+}
+
+/// Log the wizard's steps to stderr at debug level with `--verbose`, or as
+/// `RUST_LOG` directs. Subcommands otherwise run without a subscriber.
+fn init_wizard_logging(verbose: bool) {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = if verbose {
+        EnvFilter::new("narsil_mcp=debug")
+    } else {
+        match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        }
+    };
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+async fn cmd_init(
+    preset: Option<String>,
+    project: bool,
+    user: bool,
+    neural: bool,
+    wizard_args: NeuralArgs,
+) -> Result<()> {
     // If --neural flag is set, run the neural API key wizard instead
     if neural {
         use crate::config::wizard::NeuralWizard;
-        let wizard = NeuralWizard::new();
-        return wizard.run().await;
+        if wizard_args.no_color {
+            crate::config::output::set_plain(true);
+        }
+        init_wizard_logging(wizard_args.verbose);
+        if let Some(home) = &wizard_args.windows_home {
+            crate::config::editor::set_windows_home(Some(home.clone()));
+        }
+        let mut wizard = NeuralWizard::new()
+            .with_dry_run(wizard_args.dry_run)
+            .with_keyring(wizard_args.keyring)
+            .with_force(wizard_args.force)
+            .with_env_file(wizard_args.env_file.clone())
+            .with_config_path(
+                wizard_args
+                    .config_path
+                    .clone()
+                    .or_else(NeuralWizard::config_path_from_env),
+            )
+            .with_merge_args(wizard_args.merge_args)
+            .with_rerank_key(wizard_args.rerank_key.clone())
+            .with_request_timeout(wizard_args.request_timeout)
+            .with_repo_check(!wizard_args.no_repo_check)
+            .with_repos(
+                wizard_args
+                    .repos
+                    .as_deref()
+                    .map(NeuralWizard::parse_repo_list)
+                    .unwrap_or_default(),
+            );
+        if let Some(server_name) = &wizard_args.server_name {
+            wizard = wizard.with_server_name(server_name);
+        }
+        if let Some(command) = &wizard_args.command {
+            wizard = wizard.with_command(command);
+        }
+        if let Some(secs) = wizard_args.validate_timeout {
+            wizard = wizard.with_validate_timeout(std::time::Duration::from_secs(secs));
+        }
+        let json = wizard_args.json;
+        let summary = match wizard_args.provider {
+            Some(_) => Some(cmd_init_neural_headless(&wizard, wizard_args).await?),
+            None => wizard.run().await?,
+        };
+        if let Some(summary) = summary.filter(|_| json) {
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        return Ok(());
     }
     // Determine target path
     let target_path = if project {
@@ -330,7 +726,11 @@ preset: "{}"
     std::fs::write(&target_path, content)
         .with_context(|| format!("Failed to write config file: {:?}", target_path))?;
 
-    println!("✓ Created configuration file: {:?}", target_path);
+    println!(
+        "{} Created configuration file: {:?}",
+        marker(Status::Ok),
+        target_path
+    );
     println!("\nNext steps:");
     println!("  1. Edit the config file to customize settings");
     println!(
@@ -342,6 +742,334 @@ preset: "{}"
     Ok(())
 }
 
+/// Configure neural embeddings from CLI flags, without prompting
+async fn cmd_init_neural_headless(
+    wizard: &crate::config::wizard::NeuralWizard,
+    args: NeuralArgs,
+) -> Result<crate::config::wizard::WizardSummary> {
+    use crate::config::wizard::NeuralWizard;
+    use std::io::IsTerminal;
+
+    let provider = parse_provider_arg(&args.provider.unwrap_or_default())?;
+
+    let api_key = match args.api_key {
+        Some(key) => key,
+        None if !provider.requires_api_key() => provider
+            .default_location()
+            .context("--api-key is required to give the local model directory")?,
+        None if !std::io::stdin().is_terminal() => {
+            NeuralWizard::read_piped_key(&mut std::io::stdin().lock())?
+        }
+        None => anyhow::bail!(
+            "--api-key is required for {} (or pipe the key on stdin)",
+            provider.display_name()
+        ),
+    };
+
+    if let Some(env_file) = &args.env_file {
+        let summary = wizard
+            .configure_path(env_file, provider, &api_key, !args.no_validate)
+            .await?;
+        if !args.dry_run && !args.json {
+            println!(
+                "{} Wrote {} settings to {}",
+                marker(Status::Ok),
+                provider.display_name(),
+                env_file.display()
+            );
+        }
+        return Ok(summary);
+    }
+
+    let config_path = args
+        .config_path
+        .clone()
+        .or_else(NeuralWizard::config_path_from_env);
+    let editor = match (&args.editor, &config_path) {
+        (Some(editor), _) => parse_editor_arg(editor)?,
+        (None, Some(config_path)) => wizard.infer_editor_type(config_path)?,
+        (None, None) => anyhow::bail!(
+            "--editor, --config-path or --env-file is required when --provider is set"
+        ),
+    };
+
+    let summary = match &config_path {
+        Some(config_path) => {
+            wizard
+                .configure_at(provider, &api_key, editor, config_path, !args.no_validate)
+                .await?
+        }
+        None => {
+            wizard
+                .configure(provider, &api_key, editor, !args.no_validate)
+                .await?
+        }
+    };
+    if !args.dry_run && !args.json {
+        println!(
+            "{} Configured {} for {} ({})",
+            marker(Status::Ok),
+            provider.display_name(),
+            editor,
+            summary.config_path.display()
+        );
+    }
+    Ok(summary)
+}
+
+async fn cmd_remove_key(editor: String, provider: String, server_name: String) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::NeuralWizard;
+
+    let editor = parse_editor_arg(&editor)?;
+    let provider = parse_provider_arg(&provider)?;
+    let config_path = get_editor_config_path(editor);
+
+    NeuralWizard::new()
+        .with_server_name(server_name)
+        .remove_from_editor_config(&config_path, provider.env_var_name())
+        .await?;
+
+    println!(
+        "{} Removed {} from {}",
+        marker(Status::Ok),
+        provider.env_var_name(),
+        config_path.display()
+    );
+    Ok(())
+}
+
+async fn cmd_uninstall(editor: String, server_name: String) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::NeuralWizard;
+
+    let config_path = get_editor_config_path(parse_editor_arg(&editor)?);
+    let removed = NeuralWizard::new()
+        .with_server_name(server_name.as_str())
+        .uninstall_from_editor_config(&config_path)
+        .await?;
+
+    if removed {
+        println!(
+            "{} Removed {} from {}",
+            marker(Status::Ok),
+            server_name,
+            config_path.display()
+        );
+    } else {
+        println!(
+            "{} is not configured in {}",
+            server_name,
+            config_path.display()
+        );
+    }
+    Ok(())
+}
+
+async fn cmd_neural(state: Toggle, editor: String, server_name: String) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::NeuralWizard;
+
+    let config_path = get_editor_config_path(parse_editor_arg(&editor)?);
+    let wizard = NeuralWizard::new().with_server_name(server_name);
+
+    match state {
+        Toggle::On => wizard.enable_neural(&config_path).await?,
+        Toggle::Off => wizard.disable_neural(&config_path).await?,
+    }
+
+    println!(
+        "{} Neural embeddings {} in {}",
+        marker(Status::Ok),
+        if state == Toggle::On {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        config_path.display()
+    );
+    Ok(())
+}
+
+fn cmd_path(editor: &str) -> Result<()> {
+    let editor = parse_editor_arg(editor)?;
+    print!("{}", format_config_paths(editor));
+    Ok(())
+}
+
+/// Every place `editor` may keep its config, one per line with whether it
+/// exists. The path config commands edit is marked with `*`.
+fn format_config_paths(editor: crate::config::editor::EditorType) -> String {
+    use crate::config::editor::{get_editor_config_path, get_editor_config_paths};
+
+    let resolved = get_editor_config_path(editor);
+    get_editor_config_paths(editor)
+        .iter()
+        .map(|path| {
+            format!(
+                "{} {} ({})\n",
+                if *path == resolved { "*" } else { " " },
+                path.display(),
+                if path.exists() { "exists" } else { "missing" }
+            )
+        })
+        .collect()
+}
+
+fn cmd_list(server_name: &str, json: bool) -> Result<()> {
+    use crate::config::wizard::{NeuralWizard, ServerSummary};
+
+    let servers: Vec<ServerSummary> = NeuralWizard::list_configured_servers(server_name)
+        .iter()
+        .map(|server| server.summary())
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&servers)?);
+        return Ok(());
+    }
+    if servers.is_empty() {
+        println!("No editors have {} configured", server_name);
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<7} {:<28} {:<28} Config",
+        "Editor", "Neural", "Provider", "Embedding key"
+    );
+    println!("{:-<110}", "");
+    for server in servers {
+        let provider = match &server.meta {
+            Some(meta) => match &meta.model {
+                Some(model) => format!("{} ({})", meta.provider, model),
+                None => meta.provider.clone(),
+            },
+            None => "-".to_string(),
+        };
+        let key = match &server.embedding_env {
+            Some(env) => format!("{}={}", env.name, env.value),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<16} {:<7} {:<28} {:<28} {}",
+            server.editor.to_string(),
+            if server.neural { "yes" } else { "no" },
+            provider,
+            key,
+            server.config_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn cmd_doctor(server_name: &str, json: bool) -> Result<()> {
+    use crate::config::wizard::{NeuralWizard, Severity};
+
+    let reports = NeuralWizard::doctor(server_name);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        let failed = reports.iter().filter(|r| r.has_errors()).count();
+        if failed > 0 {
+            anyhow::bail!("{} editor config(s) have errors", failed);
+        }
+        return Ok(());
+    }
+    if reports.is_empty() {
+        println!("No editor configs found");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for report in &reports {
+        let status = if report.has_errors() {
+            failed += 1;
+            Status::Fail
+        } else if report.issues.is_empty() {
+            Status::Ok
+        } else {
+            Status::Warn
+        };
+        println!(
+            "{} {} ({})",
+            marker(status),
+            report.editor_type,
+            report.config_path.display()
+        );
+        for issue in &report.issues {
+            let label = match issue.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            println!("    {}: {}", label, issue.message);
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} editor config(s) have errors", failed);
+    }
+    Ok(())
+}
+
+fn cmd_estimate(
+    repos: Option<String>,
+    provider: &str,
+    model: Option<String>,
+    price_per_1k: Option<f64>,
+) -> Result<()> {
+    use crate::config::estimate::{estimate_repos, format_cost, price_per_1k_tokens};
+    use crate::config::wizard::NeuralWizard;
+
+    let provider = parse_provider_arg(provider)?;
+    let mut repos: Vec<PathBuf> = repos
+        .as_deref()
+        .map(NeuralWizard::parse_repo_list)
+        .unwrap_or_default()
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    if repos.is_empty() {
+        repos.push(PathBuf::from("."));
+    }
+
+    let model = model.or_else(|| {
+        Some(provider.default_model())
+            .filter(|m| !m.is_empty())
+            .map(str::to_string)
+    });
+    let estimate = estimate_repos(&repos)?;
+
+    println!("Provider: {}", provider.display_name());
+    if let Some(model) = &model {
+        println!("Model:    {}", model);
+    }
+    println!("Files:    {}", estimate.files);
+    println!("Tokens:   ~{}", estimate.tokens);
+
+    match price_per_1k.or_else(|| model.as_deref().and_then(price_per_1k_tokens)) {
+        Some(price) => println!("Cost:     ~{}", format_cost(estimate.cost(price))),
+        None => println!("Cost:     unknown (pass --price-per-1k to estimate it)"),
+    }
+    Ok(())
+}
+
+fn parse_provider_arg(name: &str) -> Result<crate::config::wizard::ApiProvider> {
+    crate::config::wizard::ApiProvider::parse(name).with_context(|| {
+        format!(
+            "Unknown provider '{}'. Valid providers: voyage, openai, custom, cohere, gemini, azure, ollama, onnx, openai-compatible",
+            name
+        )
+    })
+}
+
+fn parse_editor_arg(name: &str) -> Result<crate::config::editor::EditorType> {
+    crate::config::editor::EditorType::parse(name).with_context(|| {
+        format!(
+            "Unknown editor '{}'. Valid editors: claude-desktop, claude-code, zed, vscode, jetbrains, cursor, windsurf, continue, sublime-text, neovim",
+            name
+        )
+    })
+}
+
 fn cmd_preset(preset: String, project: bool) -> Result<()> {
     // Validate preset name
     if !["minimal", "balanced", "full", "security-focused"].contains(&preset.as_str()) {
@@ -379,7 +1107,12 @@ preset: "{}"
     // Write config (overwrite existing)
     std::fs::write(&target_path, content)?;
 
-    println!("✓ Applied '{}' preset to: {:?}", preset, target_path);
+    println!(
+        "{} Applied '{}' preset to: {:?}",
+        marker(Status::Ok),
+        preset,
+        target_path
+    );
     println!("\nPreset summary:");
     match preset.as_str() {
         "minimal" => println!("  20-30 tools for fast, lightweight editing"),
@@ -421,6 +1154,93 @@ fn cmd_export(resolved: bool, format: OutputFormat) -> Result<()> {
     Ok(())
 }
 
+fn cmd_export_server(editor: String, server_name: String, output: Option<PathBuf>) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::NeuralWizard;
+
+    let config_path = get_editor_config_path(parse_editor_arg(&editor)?);
+    let export = NeuralWizard::new()
+        .with_server_name(server_name)
+        .export_server(&config_path)?;
+
+    let Some(output) = output else {
+        println!("{}", export.to_json()?);
+        return Ok(());
+    };
+    let contents = if output.extension().is_some_and(|ext| ext == "toml") {
+        export.to_toml()?
+    } else {
+        export.to_json()?
+    };
+    std::fs::write(&output, contents)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    println!(
+        "{} Exported {} to {}",
+        marker(Status::Ok),
+        config_path.display(),
+        output.display()
+    );
+    let redacted = export.redacted();
+    if !redacted.is_empty() {
+        println!("  Redacted: {}", redacted.join(", "));
+    }
+    Ok(())
+}
+
+async fn cmd_import(
+    file: PathBuf,
+    editor: String,
+    server_name: String,
+    dry_run: bool,
+) -> Result<()> {
+    use crate::config::editor::get_editor_config_path;
+    use crate::config::wizard::{NeuralWizard, ServerExport};
+    use std::io::IsTerminal;
+
+    let content = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let export = ServerExport::parse(&content)?;
+    let config_path = get_editor_config_path(parse_editor_arg(&editor)?);
+
+    // Redacted secrets come from the environment, else a prompt
+    let skipped = NeuralWizard::new()
+        .with_server_name(server_name)
+        .with_dry_run(dry_run)
+        .import_server(&config_path, &export, |name| {
+            if let Some(value) = std::env::var(name).ok().filter(|v| !v.trim().is_empty()) {
+                return Ok(Some(value));
+            }
+            if !std::io::stdin().is_terminal() {
+                return Ok(None);
+            }
+            let value = NeuralWizard::sanitize_api_key(&NeuralWizard::read_secret(&format!(
+                "{} (enter to skip): ",
+                name
+            ))?);
+            Ok((!value.is_empty()).then_some(value))
+        })
+        .await?;
+
+    if dry_run {
+        return Ok(());
+    }
+    println!(
+        "{} Imported {} into {}",
+        marker(Status::Ok),
+        file.display(),
+        config_path.display()
+    );
+    for name in skipped {
+        println!(
+            "{} {} was left out; set it before starting the server",
+            marker(Status::Warn),
+            name
+        );
+    }
+    Ok(())
+}
+
 fn cmd_tools_list(category: Option<String>, format: OutputFormat) -> Result<()> {
     let tools: Vec<_> = if let Some(cat) = category {
         TOOL_METADATA
@@ -608,6 +1428,31 @@ mod tests {
         assert!(path.to_string_lossy().ends_with("config.yaml"));
     }
 
+    #[test]
+    fn test_format_config_paths_marks_resolved_path() {
+        use crate::config::editor::{get_editor_config_path, get_editor_config_paths, EditorType};
+
+        for editor in [EditorType::VSCode, EditorType::Continue] {
+            let output = format_config_paths(editor);
+            assert_eq!(
+                output.lines().count(),
+                get_editor_config_paths(editor).len()
+            );
+
+            let marked: Vec<&str> = output.lines().filter(|l| l.starts_with('*')).collect();
+            let resolved = get_editor_config_path(editor);
+            let status = if resolved.exists() {
+                "exists"
+            } else {
+                "missing"
+            };
+            assert_eq!(
+                marked,
+                vec![format!("* {} ({})", resolved.display(), status)]
+            );
+        }
+    }
+
     #[test]
     fn test_output_format_variants() {
         // Ensure OutputFormat enum has expected variants
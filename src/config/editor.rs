@@ -3,16 +3,37 @@
 /// Maps MCP client names to appropriate tool presets based on editor capabilities
 /// and performance characteristics.
 use super::preset::Preset;
+use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Env var giving the Windows home (`C:\Users\me` or `/mnt/c/Users/me`)
+/// whose editor configs are used from inside WSL
+pub const WINDOWS_HOME_VAR: &str = "NARSIL_WINDOWS_HOME";
+
+/// Settings file the Sublime Text MCP package reads from `Packages/User`
+pub const SUBLIME_SETTINGS_FILE: &str = "MCP.sublime-settings";
+
+/// Set by `--windows-home`; [`WINDOWS_HOME_VAR`] is checked when unset
+static WINDOWS_HOME: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Serialized with the names [`EditorType::parse`] accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum EditorType {
     ClaudeDesktop,
     ClaudeCode,
     Zed,
+    #[serde(rename = "vscode")]
     VSCode,
+    #[serde(rename = "jetbrains")]
     JetBrains,
+    Cursor,
+    Windsurf,
+    Continue,
+    SublimeText,
+    Neovim,
 }
 
 impl fmt::Display for EditorType {
@@ -23,6 +44,30 @@ impl fmt::Display for EditorType {
             EditorType::Zed => write!(f, "Zed"),
             EditorType::VSCode => write!(f, "VS Code"),
             EditorType::JetBrains => write!(f, "JetBrains IDEs"),
+            EditorType::Cursor => write!(f, "Cursor"),
+            EditorType::Windsurf => write!(f, "Windsurf"),
+            EditorType::Continue => write!(f, "Continue"),
+            EditorType::SublimeText => write!(f, "Sublime Text"),
+            EditorType::Neovim => write!(f, "Neovim"),
+        }
+    }
+}
+
+impl EditorType {
+    /// Parse an editor name as given on the command line
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "claude-desktop" | "claude_desktop" | "claude" => Some(EditorType::ClaudeDesktop),
+            "claude-code" | "claude_code" => Some(EditorType::ClaudeCode),
+            "zed" => Some(EditorType::Zed),
+            "vscode" | "vs-code" | "code" => Some(EditorType::VSCode),
+            "jetbrains" | "intellij" | "idea" => Some(EditorType::JetBrains),
+            "cursor" => Some(EditorType::Cursor),
+            "windsurf" | "codeium" => Some(EditorType::Windsurf),
+            "continue" | "continue-dev" | "continue.dev" => Some(EditorType::Continue),
+            "sublime-text" | "sublime_text" | "sublime" | "subl" => Some(EditorType::SublimeText),
+            "neovim" | "nvim" | "mcphub" => Some(EditorType::Neovim),
+            _ => None,
         }
     }
 }
@@ -35,13 +80,156 @@ pub struct EditorConfig {
 }
 
 /// Get the config file path for a specific editor
+///
+/// Returns the first candidate from [`get_editor_config_paths`] that exists,
+/// or the preferred one when none do.
 pub fn get_editor_config_path(editor: EditorType) -> PathBuf {
+    let mut candidates = get_editor_config_paths(editor);
+    let index = candidates
+        .iter()
+        .position(|path| path.exists())
+        .unwrap_or(0);
+    candidates.swap_remove(index)
+}
+
+/// The candidates from [`get_editor_config_paths`] that exist, e.g. both
+/// VS Code and VS Code Insiders configs
+pub fn get_existing_editor_config_paths(editor: EditorType) -> Vec<PathBuf> {
+    get_editor_config_paths(editor)
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// All locations an editor may keep its config in, most preferred first.
+///
+/// Under WSL the configs of editors running on the Windows host come last.
+pub fn get_editor_config_paths(editor: EditorType) -> Vec<PathBuf> {
+    let mut paths = native_editor_config_paths(editor);
+    if let Some(home) = windows_home() {
+        paths.extend(windows_host_config_paths(editor, &home));
+    }
+    paths
+}
+
+fn native_editor_config_paths(editor: EditorType) -> Vec<PathBuf> {
+    match editor {
+        EditorType::ClaudeDesktop => vec![get_claude_desktop_config_path()],
+        EditorType::ClaudeCode => get_claude_code_config_paths(),
+        EditorType::Zed => get_zed_config_paths(),
+        EditorType::VSCode => get_vscode_config_paths(),
+        EditorType::JetBrains => get_jetbrains_config_paths(),
+        EditorType::Cursor => vec![get_cursor_config_path()],
+        EditorType::Windsurf => vec![get_windsurf_config_path()],
+        EditorType::Continue => get_continue_config_paths(),
+        EditorType::SublimeText => sublime_user_dir()
+            .map(|dir| dir.join(SUBLIME_SETTINGS_FILE))
+            .into_iter()
+            .collect(),
+        EditorType::Neovim => vec![get_mcphub_config_path()],
+    }
+}
+
+/// Use the editor configs under this Windows home from inside WSL, as for
+/// `--windows-home`
+pub fn set_windows_home(home: Option<PathBuf>) {
+    if let Ok(mut windows_home) = WINDOWS_HOME.write() {
+        *windows_home = home;
+    }
+}
+
+/// The Windows user's home as seen from WSL: the `--windows-home` or
+/// [`WINDOWS_HOME_VAR`] override, else `USERPROFILE` when WSLENV passes it
+/// through, else `/mnt/c/Users/$USER` if it exists. `None` outside WSL
+/// unless overridden.
+fn windows_home() -> Option<PathBuf> {
+    let configured = WINDOWS_HOME
+        .read()
+        .ok()
+        .and_then(|home| home.clone())
+        .or_else(|| {
+            std::env::var_os(WINDOWS_HOME_VAR)
+                .filter(|home| !home.is_empty())
+                .map(PathBuf::from)
+        });
+    if let Some(home) = configured {
+        return Some(wsl_path(&home.to_string_lossy()));
+    }
+    if !is_wsl() {
+        return None;
+    }
+
+    if let Some(profile) = std::env::var_os("USERPROFILE").filter(|p| !p.is_empty()) {
+        return Some(wsl_path(&profile.to_string_lossy()));
+    }
+    let user = std::env::var_os("USER")?;
+    let home = Path::new("/mnt/c/Users").join(user);
+    home.is_dir().then_some(home)
+}
+
+/// Whether this is a WSL kernel, going by `/proc/version`
+fn is_wsl() -> bool {
+    static WSL: OnceLock<bool> = OnceLock::new();
+    *WSL.get_or_init(|| {
+        std::fs::read_to_string("/proc/version").is_ok_and(|version| is_wsl_kernel(&version))
+    })
+}
+
+fn is_wsl_kernel(proc_version: &str) -> bool {
+    proc_version.to_lowercase().contains("microsoft")
+}
+
+/// Translate a Windows path like `C:\Users\me` to its WSL mount
+/// `/mnt/c/Users/me`; other paths are returned as they are
+pub fn wsl_path(path: &str) -> PathBuf {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), Some('\\' | '/') | None) if drive.is_ascii_alphabetic() => {
+            let rest = path[2..].replace('\\', "/");
+            PathBuf::from(format!(
+                "/mnt/{}/{}",
+                drive.to_ascii_lowercase(),
+                rest.trim_start_matches('/')
+            ))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Where an editor running on Windows keeps its config, under the Windows
+/// `home` as mounted in WSL
+fn windows_host_config_paths(editor: EditorType, home: &Path) -> Vec<PathBuf> {
+    let roaming = home.join("AppData").join("Roaming");
     match editor {
-        EditorType::ClaudeDesktop => get_claude_desktop_config_path(),
-        EditorType::ClaudeCode => get_claude_code_config_path(),
-        EditorType::Zed => get_zed_config_path(),
-        EditorType::VSCode => get_vscode_config_path(),
-        EditorType::JetBrains => get_jetbrains_config_path(),
+        EditorType::ClaudeDesktop => {
+            vec![roaming.join("Claude").join("claude_desktop_config.json")]
+        }
+        // The Claude Code CLI runs inside WSL itself
+        EditorType::ClaudeCode => Vec::new(),
+        EditorType::Zed => vec![roaming.join("Zed").join("settings.json")],
+        EditorType::VSCode => ["Code", "Code - Insiders"]
+            .iter()
+            .map(|app| roaming.join(app).join("User").join("mcp.json"))
+            .collect(),
+        EditorType::JetBrains => latest_jetbrains_dir(&roaming.join("JetBrains"))
+            .map(|dir| dir.join("mcp.json"))
+            .into_iter()
+            .collect(),
+        EditorType::Cursor => vec![home.join(".cursor").join("mcp.json")],
+        EditorType::Windsurf => vec![home
+            .join(".codeium")
+            .join("windsurf")
+            .join("mcp_config.json")],
+        EditorType::Continue => vec![
+            home.join(".continue").join("config.yaml"),
+            home.join(".continue").join("config.json"),
+        ],
+        EditorType::SublimeText => vec![roaming
+            .join("Sublime Text")
+            .join("Packages")
+            .join("User")
+            .join(SUBLIME_SETTINGS_FILE)],
+        EditorType::Neovim => vec![home.join(".config").join("mcphub").join("servers.json")],
     }
 }
 
@@ -55,6 +243,11 @@ pub fn detect_available_editors() -> Vec<EditorConfig> {
         EditorType::Zed,
         EditorType::VSCode,
         EditorType::JetBrains,
+        EditorType::Cursor,
+        EditorType::Windsurf,
+        EditorType::Continue,
+        EditorType::SublimeText,
+        EditorType::Neovim,
     ] {
         let config_path = get_editor_config_path(editor_type);
         let exists = config_path.exists();
@@ -93,7 +286,18 @@ fn get_claude_desktop_config_path() -> PathBuf {
         }
     }
 
-    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(config_home) = xdg_config_home() {
+            config_home
+                .join("Claude")
+                .join("claude_desktop_config.json")
+        } else {
+            PathBuf::from("claude_desktop_config.json")
+        }
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
     {
         use directories::ProjectDirs;
         if let Some(proj_dirs) = ProjectDirs::from("com", "Anthropic", "Claude") {
@@ -109,6 +313,27 @@ fn get_claude_desktop_config_path() -> PathBuf {
     }
 }
 
+/// `$XDG_CONFIG_HOME`, or `~/.config` when it is unset or empty
+#[cfg(target_os = "linux")]
+fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Claude Code's project-level `.mcp.json` when the project has one, else
+/// the user config
+fn get_claude_code_config_paths() -> Vec<PathBuf> {
+    let project = std::env::current_dir().unwrap().join(".mcp.json");
+    let user = get_claude_code_config_path();
+    if project.exists() {
+        vec![project, user]
+    } else {
+        vec![user, project]
+    }
+}
+
 fn get_claude_code_config_path() -> PathBuf {
     // Check HOME (Unix) or USERPROFILE (Windows)
     if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
@@ -120,43 +345,313 @@ fn get_claude_code_config_path() -> PathBuf {
     }
 }
 
-fn get_zed_config_path() -> PathBuf {
+/// The project's `.zed/settings.json` when the project has one, else the
+/// user settings
+fn get_zed_config_paths() -> Vec<PathBuf> {
+    let project = std::env::current_dir()
+        .unwrap()
+        .join(".zed")
+        .join("settings.json");
+    let mut paths = get_zed_user_config_paths();
+    if project.exists() {
+        paths.insert(0, project);
+    } else {
+        paths.push(project);
+    }
+    paths
+}
+
+fn get_zed_user_config_paths() -> Vec<PathBuf> {
     #[cfg(target_os = "windows")]
     {
         use directories::ProjectDirs;
         if let Some(proj_dirs) = ProjectDirs::from("dev", "zed", "Zed") {
-            proj_dirs.config_dir().join("settings.json")
+            vec![proj_dirs.config_dir().join("settings.json")]
         } else {
-            PathBuf::from("settings.json")
+            vec![PathBuf::from("settings.json")]
         }
     }
 
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(target_os = "linux")]
     {
-        // Zed uses ~/.config/zed on Unix-like systems
+        let mut paths = Vec::new();
+        if let Some(config_home) = xdg_config_home() {
+            paths.push(config_home.join("zed").join("settings.json"));
+        }
+        // The Flatpak build keeps its config inside the sandbox
         if let Some(home) = std::env::var_os("HOME") {
-            PathBuf::from(home)
+            paths.push(
+                PathBuf::from(home)
+                    .join(".var")
+                    .join("app")
+                    .join("dev.zed.Zed")
+                    .join("config")
+                    .join("zed")
+                    .join("settings.json"),
+            );
+        }
+        if paths.is_empty() {
+            paths.push(PathBuf::from("settings.json"));
+        }
+        paths
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        // Zed uses ~/.config/zed on other Unix-like systems
+        if let Some(home) = std::env::var_os("HOME") {
+            vec![PathBuf::from(home)
                 .join(".config")
                 .join("zed")
-                .join("settings.json")
+                .join("settings.json")]
         } else {
-            PathBuf::from("settings.json")
+            vec![PathBuf::from("settings.json")]
         }
     }
 }
 
-fn get_vscode_config_path() -> PathBuf {
-    std::env::current_dir()
+/// The workspace `.vscode/mcp.json`, then the user-level config of VS Code
+/// and VS Code Insiders
+fn get_vscode_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![std::env::current_dir()
         .unwrap()
         .join(".vscode")
-        .join("mcp.json")
+        .join("mcp.json")];
+
+    for app in ["Code", "Code - Insiders"] {
+        if let Some(user_dir) = vscode_user_dir(app) {
+            paths.push(user_dir.join("mcp.json"));
+        }
+    }
+
+    paths
+}
+
+/// The `User` settings directory of a VS Code build
+fn vscode_user_dir(app: &str) -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join(app)
+                .join("User")
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join(app).join("User"))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        xdg_config_home().map(|config_home| config_home.join(app).join("User"))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    {
+        std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".config").join(app).join("User"))
+    }
 }
 
-fn get_jetbrains_config_path() -> PathBuf {
-    std::env::current_dir()
+/// JetBrains products whose config dirs are named `<Product><version>`
+const JETBRAINS_PRODUCTS: &[&str] = &[
+    "IntelliJIdea",
+    "IdeaIC",
+    "PyCharm",
+    "PyCharmCE",
+    "GoLand",
+    "WebStorm",
+    "PhpStorm",
+    "RubyMine",
+    "CLion",
+    "Rider",
+    "RustRover",
+    "DataGrip",
+    "AndroidStudio",
+];
+
+/// The project's `.idea/mcp.json`, then `mcp.json` in the config dir of the
+/// most recent JetBrains IDE install
+fn get_jetbrains_config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![std::env::current_dir()
         .unwrap()
         .join(".idea")
-        .join("mcp.json")
+        .join("mcp.json")];
+    if let Some(product_dir) = jetbrains_config_root().and_then(|root| latest_jetbrains_dir(&root))
+    {
+        paths.push(product_dir.join("mcp.json"));
+    }
+    paths
+}
+
+/// The directory JetBrains IDEs keep their per-version config dirs in
+fn jetbrains_config_root() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join("JetBrains")
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("JetBrains"))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        xdg_config_home().map(|config_home| config_home.join("JetBrains"))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("JetBrains"))
+    }
+}
+
+/// Split a config dir name like `IntelliJIdea2024.1` into a known product
+/// and its version numbers
+fn parse_jetbrains_dir_name(name: &str) -> Option<(&str, Vec<u32>)> {
+    let split = name.find(|c: char| c.is_ascii_digit())?;
+    let (product, version) = name.split_at(split);
+    if !JETBRAINS_PRODUCTS.contains(&product) {
+        return None;
+    }
+    let version = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    Some((product, version))
+}
+
+/// The config dir of the newest JetBrains IDE version under `root`, across
+/// all products
+fn latest_jetbrains_dir(root: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let (_, version) = parse_jetbrains_dir_name(&name)?;
+            Some((version, entry.path()))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Cursor reads `.cursor/mcp.json` in the project, falling back to the
+/// global `~/.cursor/mcp.json`
+fn get_cursor_config_path() -> PathBuf {
+    let project = std::env::current_dir()
+        .unwrap()
+        .join(".cursor")
+        .join("mcp.json");
+    if project.exists() {
+        return project;
+    }
+
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        PathBuf::from(home).join(".cursor").join("mcp.json")
+    } else {
+        project
+    }
+}
+
+fn get_windsurf_config_path() -> PathBuf {
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        PathBuf::from(home)
+            .join(".codeium")
+            .join("windsurf")
+            .join("mcp_config.json")
+    } else {
+        PathBuf::from("mcp_config.json")
+    }
+}
+
+/// Continue's `~/.continue/config.yaml`, then the older `config.json`
+fn get_continue_config_paths() -> Vec<PathBuf> {
+    let dir = match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(".continue"),
+        None => PathBuf::from(".continue"),
+    };
+    vec![dir.join("config.yaml"), dir.join("config.json")]
+}
+
+/// Sublime Text's `Packages/User` directory, where user settings live
+fn sublime_user_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join("Sublime Text")
+                .join("Packages")
+                .join("User")
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var_os("APPDATA").map(|appdata| {
+            PathBuf::from(appdata)
+                .join("Sublime Text")
+                .join("Packages")
+                .join("User")
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        xdg_config_home().map(|config_home| {
+            config_home
+                .join("sublime-text")
+                .join("Packages")
+                .join("User")
+        })
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join(".config")
+                .join("sublime-text")
+                .join("Packages")
+                .join("User")
+        })
+    }
+}
+
+/// mcphub.nvim's `servers.json`, under `$XDG_CONFIG_HOME/mcphub` on Linux
+/// and `~/.config/mcphub` elsewhere
+fn get_mcphub_config_path() -> PathBuf {
+    #[cfg(target_os = "linux")]
+    {
+        match xdg_config_home() {
+            Some(config_home) => config_home.join("mcphub").join("servers.json"),
+            None => PathBuf::from("servers.json"),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            Some(home) => PathBuf::from(home)
+                .join(".config")
+                .join("mcphub")
+                .join("servers.json"),
+            None => PathBuf::from("servers.json"),
+        }
+    }
 }
 
 /// Map an editor name to a preset
@@ -311,6 +806,144 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_editor_type_parse() {
+        assert_eq!(EditorType::parse("claude"), Some(EditorType::ClaudeDesktop));
+        assert_eq!(
+            EditorType::parse("claude-code"),
+            Some(EditorType::ClaudeCode)
+        );
+        assert_eq!(EditorType::parse("Zed"), Some(EditorType::Zed));
+        assert_eq!(EditorType::parse("vscode"), Some(EditorType::VSCode));
+        assert_eq!(EditorType::parse("jetbrains"), Some(EditorType::JetBrains));
+        assert_eq!(EditorType::parse("Cursor"), Some(EditorType::Cursor));
+        assert_eq!(EditorType::parse("windsurf"), Some(EditorType::Windsurf));
+        assert_eq!(EditorType::parse("continue"), Some(EditorType::Continue));
+        assert_eq!(
+            EditorType::parse("sublime-text"),
+            Some(EditorType::SublimeText)
+        );
+        assert_eq!(EditorType::parse("Sublime"), Some(EditorType::SublimeText));
+        assert_eq!(EditorType::parse("nvim"), Some(EditorType::Neovim));
+        assert_eq!(EditorType::parse("neovim"), Some(EditorType::Neovim));
+        assert_eq!(EditorType::parse("notepad"), None);
+    }
+
+    #[test]
+    fn test_parse_jetbrains_dir_name() {
+        assert_eq!(
+            parse_jetbrains_dir_name("IntelliJIdea2024.1"),
+            Some(("IntelliJIdea", vec![2024, 1]))
+        );
+        assert_eq!(
+            parse_jetbrains_dir_name("GoLand2023.2.1"),
+            Some(("GoLand", vec![2023, 2, 1]))
+        );
+        assert_eq!(
+            parse_jetbrains_dir_name("PyCharmCE2024.2"),
+            Some(("PyCharmCE", vec![2024, 2]))
+        );
+        assert_eq!(parse_jetbrains_dir_name("IntelliJIdea2024.1-backup"), None);
+        assert_eq!(parse_jetbrains_dir_name("consentOptions"), None);
+        assert_eq!(parse_jetbrains_dir_name("Notepad2024.1"), None);
+    }
+
+    #[test]
+    fn test_latest_jetbrains_dir_picks_newest_version() {
+        let temp = tempfile::tempdir().unwrap();
+        for name in [
+            "IntelliJIdea2023.3",
+            "IntelliJIdea2024.1",
+            "GoLand2024.1.2",
+            "PyCharm2023.10",
+            "IntelliJIdea2025.1-backup",
+            "consentOptions",
+        ] {
+            std::fs::create_dir(temp.path().join(name)).unwrap();
+        }
+        // Files are not config dirs
+        std::fs::write(temp.path().join("WebStorm2030.1"), "").unwrap();
+
+        assert_eq!(
+            latest_jetbrains_dir(temp.path()),
+            Some(temp.path().join("GoLand2024.1.2"))
+        );
+    }
+
+    #[test]
+    fn test_latest_jetbrains_dir_without_installs() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(latest_jetbrains_dir(temp.path()), None);
+        assert_eq!(latest_jetbrains_dir(&temp.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_is_wsl_kernel() {
+        assert!(is_wsl_kernel(
+            "Linux version 5.15.153.1-microsoft-standard-WSL2 (root@1c602f52c2e4)"
+        ));
+        assert!(is_wsl_kernel("Linux version 4.4.0-19041-Microsoft"));
+        assert!(!is_wsl_kernel(
+            "Linux version 6.8.0-45-generic (buildd@lcy02)"
+        ));
+    }
+
+    #[test]
+    fn test_wsl_path() {
+        assert_eq!(wsl_path(r"C:\Users\me"), PathBuf::from("/mnt/c/Users/me"));
+        assert_eq!(wsl_path("D:/Users/me/"), PathBuf::from("/mnt/d/Users/me/"));
+        assert_eq!(wsl_path("C:"), PathBuf::from("/mnt/c/"));
+        assert_eq!(
+            wsl_path("/mnt/c/Users/me"),
+            PathBuf::from("/mnt/c/Users/me")
+        );
+        assert_eq!(wsl_path("relative"), PathBuf::from("relative"));
+    }
+
+    #[test]
+    fn test_windows_host_config_paths() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let roaming = home.join("AppData").join("Roaming");
+
+        assert_eq!(
+            windows_host_config_paths(EditorType::VSCode, home),
+            vec![
+                roaming.join("Code").join("User").join("mcp.json"),
+                roaming
+                    .join("Code - Insiders")
+                    .join("User")
+                    .join("mcp.json"),
+            ]
+        );
+        assert_eq!(
+            windows_host_config_paths(EditorType::Cursor, home),
+            vec![home.join(".cursor").join("mcp.json")]
+        );
+        assert_eq!(
+            windows_host_config_paths(EditorType::ClaudeDesktop, home),
+            vec![roaming.join("Claude").join("claude_desktop_config.json")]
+        );
+        assert!(windows_host_config_paths(EditorType::ClaudeCode, home).is_empty());
+        assert_eq!(
+            windows_host_config_paths(EditorType::SublimeText, home),
+            vec![roaming
+                .join("Sublime Text")
+                .join("Packages")
+                .join("User")
+                .join("MCP.sublime-settings")]
+        );
+
+        // JetBrains only has a candidate once an IDE config dir exists
+        assert!(windows_host_config_paths(EditorType::JetBrains, home).is_empty());
+        let product = roaming.join("JetBrains").join("PyCharm2024.2");
+        std::fs::create_dir_all(&product).unwrap();
+        assert_eq!(
+            windows_host_config_paths(EditorType::JetBrains, home),
+            vec![product.join("mcp.json")]
+        );
+    }
+
     #[test]
     fn test_whitespace_handling() {
         assert_eq!(get_editor_preset(" vscode "), Some(Preset::Balanced));
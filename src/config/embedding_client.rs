@@ -0,0 +1,2281 @@
+//! Embedding API clients used by the setup wizard and the server's API embedder
+//!
+//! Each provider implements [`EmbeddingClient`] and
+//! [`ApiProvider::client`](super::wizard::ApiProvider::client) picks the
+//! implementation, so supporting a new provider means adding one impl here.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+#[cfg(any(test, feature = "test-utils"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::wizard::{
+    redact_keys, ApiProvider, NeuralWizard, AZURE_OPENAI_DEPLOYMENT_VAR, AZURE_OPENAI_ENDPOINT_VAR,
+    DEFAULT_OPENAI_BASE_URL, EMBEDDING_BASE_URL_VAR,
+};
+
+/// Timeout for a request, including any retries, unless configured
+/// otherwise
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+/// Attempts made for a request that fails transiently
+const REQUEST_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubles for each further one
+const REQUEST_BACKOFF_MS: u64 = 200;
+/// Largest response body read, so a misbehaving server can't exhaust memory
+const MAX_RESPONSE_BYTES: usize = 100 * 1024 * 1024;
+/// Voyage AI embeddings endpoint
+pub const VOYAGE_EMBEDDINGS_URL: &str = "https://api.voyageai.com/v1/embeddings";
+/// Cohere embeddings endpoint
+pub const COHERE_EMBED_URL: &str = "https://api.cohere.com/v1/embed";
+/// Gemini API base URL, under which each model has its own endpoints
+pub const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// Azure OpenAI REST API version requested
+const AZURE_API_VERSION: &str = "2024-02-01";
+/// OAuth scope requested for a Gemini service account
+const GEMINI_OAUTH_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+/// Lifetime requested for a service-account access token
+const SERVICE_ACCOUNT_TOKEN_SECS: u64 = 3600;
+/// Env var overriding the number of inputs sent per embedding request
+pub const EMBEDDING_BATCH_SIZE_VAR: &str = "EMBEDDING_BATCH_SIZE";
+/// Env var declaring the embedding dimension, for models not known here
+pub const EMBEDDING_DIMENSION_VAR: &str = "EMBEDDING_DIMENSION";
+/// Env var choosing how over-long inputs are shortened: `head`,
+/// `head-tail` or `split`
+pub const EMBEDDING_TRUNCATION_VAR: &str = "EMBEDDING_TRUNCATION";
+/// Env var holding a proxy URL for embedding API requests, taking
+/// precedence over `HTTPS_PROXY`/`HTTP_PROXY`
+pub const EMBEDDING_PROXY_VAR: &str = "EMBEDDING_PROXY";
+/// Env var holding extra HTTP headers for the Custom provider, as a JSON
+/// object or `name=value;name2=value2`
+pub const EMBEDDING_EXTRA_HEADERS_VAR: &str = "EMBEDDING_EXTRA_HEADERS";
+/// Env var capping the number of embedding requests in flight at once
+pub const EMBEDDING_MAX_CONCURRENCY_VAR: &str = "EMBEDDING_MAX_CONCURRENCY";
+/// Env var capping embedding requests per minute; `0` turns the cap off
+pub const EMBEDDING_REQUESTS_PER_MINUTE_VAR: &str = "EMBEDDING_REQUESTS_PER_MINUTE";
+/// Env var holding the embedding API request timeout in whole seconds
+pub const EMBEDDING_TIMEOUT_VAR: &str = "EMBEDDING_TIMEOUT";
+/// Rough characters per token, for estimating the size of a request
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+/// Input token limit assumed for models not in [`model_max_tokens`]
+pub const DEFAULT_MAX_TOKENS: usize = 8_000;
+
+/// An embedding provider's API
+#[async_trait::async_trait]
+pub trait EmbeddingClient: Send + Sync {
+    /// Embed each input, returning one vector per input in the same order
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Check that the provider accepts the configured key or address
+    async fn validate(&self) -> Result<()>;
+}
+
+/// Endpoint settings for a client beyond the key itself
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    /// Base URL replacing the provider default, e.g. `OPENAI_BASE_URL`; for
+    /// Azure OpenAI the resource endpoint
+    pub base_url: Option<String>,
+    /// Full embeddings URL for the Custom provider, used as is rather than
+    /// `<base_url>/embeddings`, e.g. `EMBEDDING_SERVER_ENDPOINT`
+    pub endpoint: Option<String>,
+    /// Model replacing the provider's recommended one; for Azure OpenAI the
+    /// deployment name
+    pub model: Option<String>,
+    /// Inputs per request, replacing the provider default; see
+    /// [`EMBEDDING_BATCH_SIZE_VAR`]
+    pub batch_size: Option<usize>,
+    /// Requests in flight at once, replacing the provider default; see
+    /// [`EMBEDDING_MAX_CONCURRENCY_VAR`]
+    pub max_concurrency: Option<usize>,
+    /// Requests per minute, replacing the provider default, with `Some(0)`
+    /// for no limit; see [`EMBEDDING_REQUESTS_PER_MINUTE_VAR`]
+    pub requests_per_minute: Option<u32>,
+    /// Dimension of the returned vectors, replacing the model's known one;
+    /// see [`EMBEDDING_DIMENSION_VAR`]
+    pub dimension: Option<usize>,
+    /// How inputs over the model's token limit are shortened; see
+    /// [`EMBEDDING_TRUNCATION_VAR`]
+    pub truncation: Truncation,
+    /// Proxy for every request, replacing `HTTPS_PROXY`/`HTTP_PROXY` (which
+    /// apply otherwise); see [`EMBEDDING_PROXY_VAR`]
+    pub proxy: Option<String>,
+    /// Extra headers sent with every Custom provider request; see
+    /// [`EMBEDDING_EXTRA_HEADERS_VAR`]
+    pub headers: Vec<(String, String)>,
+    /// Time a request may take including retries, replacing the short
+    /// default meant for the wizard; see [`EMBEDDING_TIMEOUT_VAR`]
+    pub timeout: Option<Duration>,
+}
+
+/// How inputs over the model's token limit are shortened before sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Truncation {
+    /// Keep the start of the input
+    #[default]
+    Head,
+    /// Keep the start and the end, e.g. a file's imports and its last
+    /// definitions
+    HeadTail,
+    /// Embed the input in limit-sized pieces and average the vectors
+    SplitAverage,
+}
+
+impl Truncation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "head" | "truncate" => Some(Truncation::Head),
+            "head-tail" | "headtail" => Some(Truncation::HeadTail),
+            "split" | "split-average" | "average" => Some(Truncation::SplitAverage),
+            _ => None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Batching and rate limit settings from the environment
+    pub fn from_env() -> Self {
+        Self {
+            batch_size: batch_size_from_env(),
+            max_concurrency: env_number(EMBEDDING_MAX_CONCURRENCY_VAR).filter(|&n| n > 0),
+            requests_per_minute: env_number(EMBEDDING_REQUESTS_PER_MINUTE_VAR),
+            dimension: dimension_from_env(),
+            truncation: std::env::var(EMBEDDING_TRUNCATION_VAR)
+                .ok()
+                .and_then(|value| Truncation::parse(&value))
+                .unwrap_or_default(),
+            proxy: proxy_url_from_env(),
+            headers: extra_headers_from_env().unwrap_or_default(),
+            timeout: timeout_from_env(),
+            ..Self::default()
+        }
+    }
+
+    fn model_or(&self, provider: ApiProvider) -> String {
+        self.model
+            .clone()
+            .unwrap_or_else(|| provider.default_model().to_string())
+    }
+}
+
+impl ApiProvider {
+    /// The API client for this provider, truncating over-long inputs,
+    /// splitting large inputs into batches the provider accepts and keeping
+    /// to its rate limits.
+    ///
+    /// For Ollama `key` is the server address.
+    pub fn client(&self, key: &str, config: &ClientConfig) -> Box<dyn EmbeddingClient> {
+        let limits = self.batch_limits();
+        let rate = self.rate_limits();
+        let limited = RateLimitedClient::new(
+            self.request_client(key, config),
+            config.max_concurrency.unwrap_or(rate.max_concurrency),
+            config
+                .requests_per_minute
+                .or(rate.requests_per_minute)
+                .filter(|&rpm| rpm > 0),
+        );
+        let model = config.model_or(*self);
+        let batched = BatchedClient::new(
+            Box::new(limited),
+            limits.size(config.batch_size),
+            limits.max_tokens,
+        )
+        .expect_dimension(config.dimension.or_else(|| self.dimension(&model)));
+
+        let max_tokens = model_max_tokens(&model).unwrap_or(DEFAULT_MAX_TOKENS);
+        Box::new(TruncatingClient::new(
+            Box::new(batched),
+            max_tokens * CHARS_PER_TOKEN,
+            config.truncation,
+        ))
+    }
+
+    /// Vector dimension `model` returns through this provider, if known.
+    ///
+    /// Azure deployments and custom endpoints can serve any model, so only
+    /// the model name is checked for them.
+    pub fn dimension(&self, model: &str) -> Option<usize> {
+        if !self.models().is_empty() && !self.models().contains(&model) {
+            return None;
+        }
+        model_dimension(model)
+    }
+
+    /// Conservative request limits for the provider, well inside the lowest
+    /// paid tier so large indexing runs don't hit HTTP 429
+    pub fn rate_limits(&self) -> RateLimits {
+        let (max_concurrency, requests_per_minute) = match self {
+            ApiProvider::Voyage => (2, Some(300)),
+            ApiProvider::OpenAI | ApiProvider::AzureOpenAI => (4, Some(500)),
+            ApiProvider::Cohere => (2, Some(100)),
+            ApiProvider::Gemini => (2, Some(150)),
+            // A local server or model only slows down with parallel requests
+            ApiProvider::Ollama | ApiProvider::LocalOnnx => (1, None),
+            ApiProvider::Custom | ApiProvider::OpenAICompatible => (4, None),
+        };
+        RateLimits {
+            max_concurrency,
+            requests_per_minute,
+        }
+    }
+
+    /// How much the provider accepts in one embeddings request
+    pub fn batch_limits(&self) -> BatchLimits {
+        match self {
+            ApiProvider::Voyage => BatchLimits {
+                default_size: 96,
+                max_inputs: 128,
+                max_tokens: 120_000,
+            },
+            ApiProvider::OpenAI | ApiProvider::AzureOpenAI => BatchLimits {
+                default_size: 2048,
+                max_inputs: 2048,
+                max_tokens: 300_000,
+            },
+            ApiProvider::Cohere => BatchLimits {
+                default_size: 96,
+                max_inputs: 96,
+                max_tokens: usize::MAX,
+            },
+            ApiProvider::Gemini => BatchLimits {
+                default_size: 100,
+                max_inputs: 100,
+                max_tokens: usize::MAX,
+            },
+            ApiProvider::Custom
+            | ApiProvider::OpenAICompatible
+            | ApiProvider::Ollama
+            | ApiProvider::LocalOnnx => BatchLimits {
+                default_size: 32,
+                max_inputs: usize::MAX,
+                max_tokens: usize::MAX,
+            },
+        }
+    }
+
+    /// The client for this provider that sends each input list as is
+    fn request_client(&self, key: &str, config: &ClientConfig) -> Box<dyn EmbeddingClient> {
+        let transport = Transport::new(config);
+        match self {
+            ApiProvider::Voyage => Box::new(VoyageClient {
+                key: key.to_string(),
+                endpoint: config
+                    .base_url
+                    .as_deref()
+                    .map(|url| format!("{}/embeddings", url.trim_end_matches('/')))
+                    .unwrap_or_else(|| VOYAGE_EMBEDDINGS_URL.to_string()),
+                model: config.model_or(*self),
+                transport,
+            }),
+            ApiProvider::OpenAI => Box::new(OpenAiClient {
+                key: key.to_string(),
+                base_url: config
+                    .base_url
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string()),
+                model: config.model_or(*self),
+                transport,
+            }),
+            ApiProvider::Cohere => Box::new(CohereClient {
+                key: key.to_string(),
+                endpoint: config
+                    .base_url
+                    .as_deref()
+                    .map(|url| format!("{}/embed", url.trim_end_matches('/')))
+                    .unwrap_or_else(|| COHERE_EMBED_URL.to_string()),
+                model: config.model_or(*self),
+                transport,
+            }),
+            ApiProvider::Gemini => Box::new(GeminiClient {
+                auth: if NeuralWizard::is_credentials_path(key) {
+                    GeminiAuth::ServiceAccount(ServiceAccount::new(&NeuralWizard::expand_path(key)))
+                } else {
+                    GeminiAuth::ApiKey(key.to_string())
+                },
+                base_url: config
+                    .base_url
+                    .clone()
+                    .unwrap_or_else(|| GEMINI_API_URL.to_string()),
+                model: config.model_or(*self),
+                transport,
+            }),
+            ApiProvider::AzureOpenAI => {
+                let deployment = config.model.as_deref().filter(|model| !model.is_empty());
+                match (&config.base_url, deployment) {
+                    (Some(endpoint), Some(deployment)) => Box::new(AzureClient {
+                        key: key.to_string(),
+                        endpoint: endpoint.trim_end_matches('/').to_string(),
+                        deployment: deployment.to_string(),
+                        transport,
+                    }),
+                    _ => Box::new(UnconfiguredClient {
+                        message: format!(
+                            "Azure OpenAI needs {} and {}",
+                            AZURE_OPENAI_ENDPOINT_VAR, AZURE_OPENAI_DEPLOYMENT_VAR
+                        ),
+                    }),
+                }
+            }
+            ApiProvider::Ollama => Box::new(OllamaClient {
+                host: key.trim_end_matches('/').to_string(),
+                model: config.model_or(*self),
+                transport,
+            }),
+            ApiProvider::LocalOnnx => Box::new(LocalModelClient {
+                dir: std::path::PathBuf::from(key),
+            }),
+            ApiProvider::Custom | ApiProvider::OpenAICompatible => {
+                let endpoint = config.endpoint.clone().or_else(|| {
+                    config
+                        .base_url
+                        .as_deref()
+                        .map(|url| format!("{}/embeddings", url.trim_end_matches('/')))
+                });
+                match endpoint {
+                    Some(endpoint) => Box::new(CustomClient {
+                        key: key.to_string(),
+                        endpoint,
+                        model: config.model_or(*self),
+                        headers: config.headers.clone(),
+                        transport,
+                    }),
+                    None => Box::new(UnconfiguredClient {
+                        message: format!(
+                            "{} needs {}",
+                            self.display_name(),
+                            EMBEDDING_BASE_URL_VAR
+                        ),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Batch size set with [`EMBEDDING_BATCH_SIZE_VAR`], if it is a positive
+/// number
+pub fn batch_size_from_env() -> Option<usize> {
+    env_number(EMBEDDING_BATCH_SIZE_VAR).filter(|&size| size > 0)
+}
+
+/// Dimension set with [`EMBEDDING_DIMENSION_VAR`], if it is a positive
+/// number
+pub fn dimension_from_env() -> Option<usize> {
+    env_number(EMBEDDING_DIMENSION_VAR).filter(|&dimension| dimension > 0)
+}
+
+/// Request timeout set with [`EMBEDDING_TIMEOUT_VAR`], if it is a positive
+/// number of seconds
+pub fn timeout_from_env() -> Option<Duration> {
+    env_number(EMBEDDING_TIMEOUT_VAR)
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Most tokens a known embedding model accepts in one input
+pub fn model_max_tokens(model: &str) -> Option<usize> {
+    let max_tokens = match model {
+        "voyage-2" => 4_000,
+        "voyage-code-2" | "voyage-large-2" => 16_000,
+        "voyage-3" | "voyage-3-large" | "voyage-3-lite" | "voyage-code-3" => 32_000,
+        "text-embedding-3-small" | "text-embedding-3-large" | "text-embedding-ada-002" => 8_191,
+        "embed-english-v3.0"
+        | "embed-multilingual-v3.0"
+        | "embed-english-light-v3.0"
+        | "embed-multilingual-light-v3.0" => 512,
+        "text-embedding-004" => 2_048,
+        "nomic-embed-text" => 8_192,
+        "mxbai-embed-large" => 512,
+        "all-minilm" => 256,
+        _ => return None,
+    };
+    Some(max_tokens)
+}
+
+/// Vector dimension of a known embedding model
+pub fn model_dimension(model: &str) -> Option<usize> {
+    let dimension = match model {
+        "voyage-code-2" | "voyage-2" | "voyage-large-2" => 1536,
+        "voyage-3" | "voyage-3-large" | "voyage-code-3" => 1024,
+        "voyage-3-lite" => 512,
+        "text-embedding-3-small" | "text-embedding-ada-002" => 1536,
+        "text-embedding-3-large" => 3072,
+        "embed-english-v3.0" | "embed-multilingual-v3.0" => 1024,
+        "embed-english-light-v3.0" | "embed-multilingual-light-v3.0" => 384,
+        "text-embedding-004" => 768,
+        "nomic-embed-text" => 768,
+        "mxbai-embed-large" => 1024,
+        "all-minilm" | "all-MiniLM-L6-v2" => 384,
+        _ => return None,
+    };
+    Some(dimension)
+}
+
+/// A number set in env var `name`, ignoring values that don't parse
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Request limits for a provider's embeddings API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Most requests in flight at once
+    pub max_concurrency: usize,
+    /// Most requests started per minute, if limited
+    pub requests_per_minute: Option<u32>,
+}
+
+/// Per-request limits of a provider's embeddings API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Inputs sent per request unless configured otherwise
+    pub default_size: usize,
+    /// Most inputs accepted in one request
+    pub max_inputs: usize,
+    /// Most tokens accepted in one request, across all inputs
+    pub max_tokens: usize,
+}
+
+impl BatchLimits {
+    /// The batch size to use, given a configured one
+    pub fn size(&self, configured: Option<usize>) -> usize {
+        configured
+            .unwrap_or(self.default_size)
+            .clamp(1, self.max_inputs)
+    }
+}
+
+/// Estimated token count of an input
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Split `input` into batches of at most `size` inputs and about
+/// `max_tokens` tokens. An input over the token limit on its own is sent
+/// alone, leaving the provider to reject or truncate it.
+fn split_batches(input: &[String], size: usize, max_tokens: usize) -> Vec<&[String]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut tokens: usize = 0;
+
+    for (i, text) in input.iter().enumerate() {
+        let text_tokens = estimate_tokens(text);
+        let full = i - start >= size || tokens.saturating_add(text_tokens) > max_tokens;
+        if i > start && full {
+            batches.push(&input[start..i]);
+            start = i;
+            tokens = 0;
+        }
+        tokens = tokens.saturating_add(text_tokens);
+    }
+    if start < input.len() {
+        batches.push(&input[start..]);
+    }
+
+    batches
+}
+
+/// The largest char boundary in `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// The smallest char boundary in `text` at or after `index`
+fn ceil_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+/// Separator put between the kept head and tail of an input
+const HEAD_TAIL_SEPARATOR: &str = "\n...\n";
+
+/// Shorten `text` to at most `max_len` bytes, or split it into pieces of
+/// that size, according to `strategy`
+fn shorten(text: &str, max_len: usize, strategy: Truncation) -> Vec<String> {
+    if text.len() <= max_len {
+        return vec![text.to_string()];
+    }
+
+    match strategy {
+        Truncation::Head => vec![text[..floor_char_boundary(text, max_len)].to_string()],
+        Truncation::HeadTail => {
+            let budget = max_len.saturating_sub(HEAD_TAIL_SEPARATOR.len());
+            let head = floor_char_boundary(text, budget / 2);
+            let tail = ceil_char_boundary(text, text.len() - (budget - budget / 2));
+            vec![format!(
+                "{}{}{}",
+                &text[..head],
+                HEAD_TAIL_SEPARATOR,
+                &text[tail..]
+            )]
+        }
+        Truncation::SplitAverage => {
+            let mut pieces = Vec::new();
+            let mut start = 0;
+            while start < text.len() {
+                let mut end = floor_char_boundary(text, start + max_len);
+                if end == start {
+                    // A single character wider than the limit
+                    end = ceil_char_boundary(text, start + 1);
+                }
+                pieces.push(text[start..end].to_string());
+                start = end;
+            }
+            pieces
+        }
+    }
+}
+
+/// Element-wise mean of the vectors for one input's pieces
+fn average(vectors: Vec<Vec<f32>>) -> Vec<f32> {
+    let count = vectors.len() as f32;
+    let mut vectors = vectors.into_iter();
+    let mut sum = vectors.next().unwrap_or_default();
+    for vector in vectors {
+        for (total, value) in sum.iter_mut().zip(vector) {
+            *total += value;
+        }
+    }
+    sum.iter_mut().for_each(|total| *total /= count);
+    sum
+}
+
+/// Shortens inputs over the model's token limit before passing them on,
+/// rather than letting the provider reject the whole request
+pub struct TruncatingClient {
+    inner: Box<dyn EmbeddingClient>,
+    max_len: usize,
+    strategy: Truncation,
+}
+
+impl TruncatingClient {
+    /// Keep inputs to `max_len` bytes, about `max_len / 4` tokens
+    pub fn new(inner: Box<dyn EmbeddingClient>, max_len: usize, strategy: Truncation) -> Self {
+        Self {
+            inner,
+            max_len: max_len.max(1),
+            strategy,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for TruncatingClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut pieces = Vec::with_capacity(input.len());
+        let mut counts = Vec::with_capacity(input.len());
+        for (i, text) in input.iter().enumerate() {
+            let shortened = shorten(text, self.max_len, self.strategy);
+            if text.len() > self.max_len {
+                tracing::info!(
+                    "Embedding input {} is about {} tokens, over the limit of {}; applying {:?}",
+                    i,
+                    estimate_tokens(text),
+                    self.max_len / CHARS_PER_TOKEN,
+                    self.strategy
+                );
+            }
+            counts.push(shortened.len());
+            pieces.extend(shortened);
+        }
+
+        let vectors = self.inner.embed(&pieces).await?;
+        if vectors.len() != pieces.len() {
+            anyhow::bail!(
+                "Expected {} embeddings from the provider, got {}",
+                pieces.len(),
+                vectors.len()
+            );
+        }
+
+        let mut vectors = vectors.into_iter();
+        Ok(counts
+            .into_iter()
+            .map(|count| average(vectors.by_ref().take(count).collect()))
+            .collect())
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.inner.validate().await
+    }
+}
+
+/// Sends inputs to another client in provider-sized batches
+pub struct BatchedClient {
+    inner: Box<dyn EmbeddingClient>,
+    size: usize,
+    max_tokens: usize,
+    dimension: Option<usize>,
+}
+
+impl BatchedClient {
+    /// Send at most `size` inputs and about `max_tokens` tokens per request
+    pub fn new(inner: Box<dyn EmbeddingClient>, size: usize, max_tokens: usize) -> Self {
+        Self {
+            inner,
+            size: size.max(1),
+            max_tokens,
+            dimension: None,
+        }
+    }
+
+    /// Fail when the provider returns vectors of any other dimension
+    pub fn expect_dimension(mut self, dimension: Option<usize>) -> Self {
+        self.dimension = dimension;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for BatchedClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(input.len());
+        for batch in split_batches(input, self.size, self.max_tokens) {
+            let embedded = self.inner.embed(batch).await?;
+            if embedded.len() != batch.len() {
+                anyhow::bail!(
+                    "Expected {} embeddings from the provider, got {}",
+                    batch.len(),
+                    embedded.len()
+                );
+            }
+            if let Some(expected) = self.dimension {
+                if let Some(vector) = embedded.iter().find(|v| v.len() != expected) {
+                    anyhow::bail!(
+                        "Provider returned {}-dimensional embeddings, expected {}; set {} if the model's dimension differs",
+                        vector.len(),
+                        expected,
+                        EMBEDDING_DIMENSION_VAR
+                    );
+                }
+            }
+            vectors.extend(embedded);
+        }
+        Ok(vectors)
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.inner.validate().await
+    }
+}
+
+/// Token bucket allowing a burst of up to a minute's worth of requests,
+/// then one request every `60 / rpm` seconds
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    state: tokio::sync::Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32) -> Self {
+        let capacity = f64::from(requests_per_minute);
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: tokio::sync::Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Wait until a request may be started
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let (tokens, refilled) = &mut *state;
+                let now = Instant::now();
+                *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.per_second)
+                    .min(self.capacity);
+                *refilled = now;
+
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - *tokens) / self.per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Limits the requests another client has in flight, and optionally how
+/// many it starts per minute
+pub struct RateLimitedClient {
+    inner: Box<dyn EmbeddingClient>,
+    permits: tokio::sync::Semaphore,
+    bucket: Option<TokenBucket>,
+}
+
+impl RateLimitedClient {
+    /// Allow `max_concurrency` requests at once and, if set,
+    /// `requests_per_minute` per minute
+    pub fn new(
+        inner: Box<dyn EmbeddingClient>,
+        max_concurrency: usize,
+        requests_per_minute: Option<u32>,
+    ) -> Self {
+        Self {
+            inner,
+            permits: tokio::sync::Semaphore::new(max_concurrency.max(1)),
+            bucket: requests_per_minute
+                .filter(|&rpm| rpm > 0)
+                .map(TokenBucket::new),
+        }
+    }
+
+    async fn admit(&self) -> Result<tokio::sync::SemaphorePermit<'_>> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .context("Embedding client was shut down")?;
+        if let Some(bucket) = &self.bucket {
+            bucket.acquire().await;
+        }
+        Ok(permit)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for RateLimitedClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let _permit = self.admit().await?;
+        self.inner.embed(input).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        let _permit = self.admit().await?;
+        self.inner.validate().await
+    }
+}
+
+#[async_trait::async_trait]
+impl<C: EmbeddingClient + ?Sized> EmbeddingClient for Arc<C> {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.as_ref().embed(input).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.as_ref().validate().await
+    }
+}
+
+/// Proxy URL set with [`EMBEDDING_PROXY_VAR`], if any
+pub fn proxy_url_from_env() -> Option<String> {
+    std::env::var(EMBEDDING_PROXY_VAR)
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+/// A proxy for all requests through `url`, still skipping the hosts listed
+/// in `NO_PROXY`
+pub fn proxy(url: &str) -> Result<reqwest::Proxy> {
+    Ok(reqwest::Proxy::all(url)
+        .with_context(|| format!("Invalid proxy URL {}", url))?
+        .no_proxy(reqwest::NoProxy::from_env()))
+}
+
+/// The proxy from [`EMBEDDING_PROXY_VAR`], if set. Without it, reqwest
+/// falls back to `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` on its own.
+pub fn proxy_from_env() -> Result<Option<reqwest::Proxy>> {
+    proxy_url_from_env().as_deref().map(proxy).transpose()
+}
+
+/// Parse extra headers given as a JSON object of strings or as
+/// `name=value` pairs separated by `;`
+pub fn parse_extra_headers(input: &str) -> Result<Vec<(String, String)>> {
+    let input = input.trim();
+    let pairs: Vec<(String, String)> = if input.starts_with('{') {
+        let object: serde_json::Map<String, Value> =
+            serde_json::from_str(input).context("Invalid JSON headers object")?;
+        object
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::String(value) => Ok((name, value)),
+                _ => anyhow::bail!("Header {} must have a string value", name),
+            })
+            .collect::<Result<_>>()?
+    } else {
+        input
+            .split(';')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| {
+                let (name, value) = pair
+                    .split_once('=')
+                    .with_context(|| format!("Expected name=value, got {}", pair.trim()))?;
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    for (name, value) in &pairs {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name {:?}", name))?;
+        reqwest::header::HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header {}", name))?;
+    }
+    Ok(pairs)
+}
+
+/// Extra headers set with [`EMBEDDING_EXTRA_HEADERS_VAR`], empty if unset
+pub fn extra_headers_from_env() -> Result<Vec<(String, String)>> {
+    match std::env::var(EMBEDDING_EXTRA_HEADERS_VAR) {
+        Ok(value) => parse_extra_headers(&value)
+            .with_context(|| format!("Invalid {}", EMBEDDING_EXTRA_HEADERS_VAR)),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+/// How requests are sent: through which proxy, and within what time
+#[derive(Debug, Clone)]
+struct Transport {
+    proxy: Option<String>,
+    timeout: Duration,
+}
+
+impl Transport {
+    fn new(config: &ClientConfig) -> Self {
+        Self {
+            proxy: config.proxy.clone(),
+            timeout: config
+                .timeout
+                .unwrap_or(Duration::from_secs(REQUEST_TIMEOUT_SECS)),
+        }
+    }
+
+    /// HTTP client with the timeout, so a hung request can't freeze the
+    /// wizard or indexing, sending requests through the proxy if given
+    fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().timeout(self.timeout);
+        if let Some(url) = &self.proxy {
+            builder = builder.proxy(proxy(url)?);
+        }
+        builder.build().context("Failed to create HTTP client")
+    }
+
+    /// Send the request `build` makes to `endpoint`.
+    ///
+    /// Rate limits, server errors and connection failures are retried with
+    /// exponential backoff, as long as the retry fits in the overall
+    /// timeout. Any other response is returned straight away.
+    async fn send(
+        &self,
+        endpoint: &str,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder + Send,
+    ) -> Result<reqwest::Response> {
+        let client = self.client()?;
+        let deadline = Instant::now() + self.timeout;
+
+        let mut attempt = 1;
+        loop {
+            let result = build(&client)
+                .timeout(deadline.saturating_duration_since(Instant::now()))
+                .send()
+                .await;
+
+            let retryable = match &result {
+                Ok(resp) => {
+                    resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                        || resp.status().is_server_error()
+                }
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            let delay = backoff_delay(attempt);
+            if !retryable || attempt >= REQUEST_ATTEMPTS || Instant::now() + delay >= deadline {
+                return result.with_context(|| format!("Network error contacting {}", endpoint));
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Post an OpenAI-style `{model, input}` embeddings request, with `key` as
+/// the bearer token unless it is empty
+async fn post_embeddings(
+    transport: &Transport,
+    endpoint: &str,
+    key: &str,
+    model: &str,
+    input: &[String],
+    headers: &[(String, String)],
+) -> Result<reqwest::Response> {
+    transport
+        .send(endpoint, |client| {
+            let mut request = client
+                .post(endpoint)
+                .json(&json!({ "model": model, "input": input }));
+            if !key.is_empty() {
+                request = request.bearer_auth(key);
+            }
+            for (name, value) in headers {
+                request = request.header(name.as_str(), value.as_str());
+            }
+            request
+        })
+        .await
+}
+
+/// Wait before retry number `attempt`: 200ms, 400ms, ... plus up to 100ms
+/// of jitter
+fn backoff_delay(attempt: u32) -> Duration {
+    let jitter = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| u64::from(now.subsec_nanos()) % 100)
+        .unwrap_or(0);
+    Duration::from_millis(REQUEST_BACKOFF_MS * 2u64.pow(attempt - 1) + jitter)
+}
+
+/// Read an error response body, trimmed for display
+async fn error_body(resp: reqwest::Response) -> String {
+    let body = resp.text().await.unwrap_or_default();
+    redact_keys(&body.chars().take(200).collect::<String>())
+}
+
+/// Read a JSON response body, giving up past [`MAX_RESPONSE_BYTES`]
+async fn read_json(mut resp: reqwest::Response) -> Result<Value> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.context("Failed to read response body")? {
+        if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+            anyhow::bail!(
+                "Response size exceeds maximum of {} bytes",
+                MAX_RESPONSE_BYTES
+            );
+        }
+        body.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&body).context("Invalid embeddings response")
+}
+
+/// Vectors from an OpenAI-style `{"data": [{"embedding": [...]}]}` response
+async fn parse_embeddings(resp: reqwest::Response) -> Result<Vec<Vec<f32>>> {
+    let body = read_json(resp).await?;
+    body["data"]
+        .as_array()
+        .context("Embeddings response has no data array")?
+        .iter()
+        .map(|item| vector_from(&item["embedding"]))
+        .collect()
+}
+
+fn vector_from(value: &Value) -> Result<Vec<f32>> {
+    value
+        .as_array()
+        .context("Embedding is not an array")?
+        .iter()
+        .map(|v| {
+            v.as_f64()
+                .map(|f| f as f32)
+                .context("Non-numeric embedding value")
+        })
+        .collect()
+}
+
+/// The probe input sent when validating a key
+fn probe() -> Vec<String> {
+    vec!["ping".to_string()]
+}
+
+/// Voyage AI, authenticated with a `pa-` key
+struct VoyageClient {
+    key: String,
+    endpoint: String,
+    model: String,
+    transport: Transport,
+}
+
+impl VoyageClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let resp = post_embeddings(
+            &self.transport,
+            &self.endpoint,
+            &self.key,
+            &self.model,
+            input,
+            &[],
+        )
+        .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            401 | 403 => {
+                anyhow::bail!("Voyage AI rejected the API key (HTTP {}): {}", status, body)
+            }
+            _ => anyhow::bail!("Voyage AI returned HTTP {}: {}", status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for VoyageClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        parse_embeddings(self.request(input).await?).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// OpenAI, or any API compatible with its embeddings endpoint
+struct OpenAiClient {
+    key: String,
+    base_url: String,
+    model: String,
+    transport: Transport,
+}
+
+impl OpenAiClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let endpoint = format!("{}/embeddings", self.base_url.trim_end_matches('/'));
+        let resp = post_embeddings(
+            &self.transport,
+            &endpoint,
+            &self.key,
+            &self.model,
+            input,
+            &[],
+        )
+        .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            401 => anyhow::bail!("OpenAI rejected the API key as invalid (HTTP 401): {}", body),
+            429 => anyhow::bail!(
+                "OpenAI rate limited the request (HTTP 429), the key may be valid - try again later: {}",
+                body
+            ),
+            _ => anyhow::bail!("OpenAI returned HTTP {}: {}", status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for OpenAiClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        parse_embeddings(self.request(input).await?).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// Cohere, whose embed endpoint takes `texts` and returns bare vectors
+struct CohereClient {
+    key: String,
+    endpoint: String,
+    model: String,
+    transport: Transport,
+}
+
+impl CohereClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let resp = self
+            .transport
+            .send(&self.endpoint, |client| {
+                client
+                    .post(&self.endpoint)
+                    .bearer_auth(&self.key)
+                    .json(&json!({
+                        "model": self.model,
+                        "texts": input,
+                        "input_type": "search_document",
+                    }))
+            })
+            .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!("Cohere rejected the API key (HTTP {}): {}", status, body),
+            _ => anyhow::bail!("Cohere returned HTTP {}: {}", status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for CohereClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = read_json(self.request(input).await?).await?;
+        body["embeddings"]
+            .as_array()
+            .context("Cohere response has no embeddings")?
+            .iter()
+            .map(vector_from)
+            .collect()
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// How Gemini requests are authenticated
+enum GeminiAuth {
+    /// An `AIza` API key
+    ApiKey(String),
+    /// A service account, exchanged for OAuth access tokens
+    ServiceAccount(ServiceAccount),
+}
+
+/// A Google service-account JSON file and the access token last issued
+/// for it
+struct ServiceAccount {
+    path: std::path::PathBuf,
+    token: tokio::sync::Mutex<Option<(String, Instant)>>,
+}
+
+impl ServiceAccount {
+    fn new(path: &str) -> Self {
+        Self {
+            path: std::path::PathBuf::from(path),
+            token: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// A current access token, signing a JWT with the account's private key
+    /// and exchanging it at `token_uri` when there's none left
+    async fn access_token(&self, transport: &Transport) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires)) = &*cached {
+            if Instant::now() + Duration::from_secs(60) < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        NeuralWizard::validate_service_account(&self.path)?;
+        let content = std::fs::read_to_string(&self.path)?;
+        let account: Value = serde_json::from_str(&content)?;
+        let field = |name: &str| account[name].as_str().unwrap_or_default().to_string();
+        let token_uri = field("token_uri");
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("System clock is before 1970")?
+            .as_secs();
+        let claims = json!({
+            "iss": field("client_email"),
+            "scope": GEMINI_OAUTH_SCOPE,
+            "aud": token_uri,
+            "iat": now,
+            "exp": now + SERVICE_ACCOUNT_TOKEN_SECS,
+        });
+        let key = jsonwebtoken::EncodingKey::from_rsa_pem(field("private_key").as_bytes())
+            .with_context(|| format!("Invalid private_key in {}", self.path.display()))?;
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &key,
+        )
+        .context("Failed to sign the service-account token request")?;
+
+        let resp = transport
+            .send(&token_uri, |client| {
+                client.post(&token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            })
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = error_body(resp).await;
+            anyhow::bail!(
+                "Google rejected the service account in {} (HTTP {}): {}",
+                self.path.display(),
+                status,
+                body
+            );
+        }
+
+        let body = read_json(resp).await?;
+        let token = body["access_token"]
+            .as_str()
+            .context("Token response has no access_token")?
+            .to_string();
+        let lifetime = body["expires_in"]
+            .as_u64()
+            .unwrap_or(SERVICE_ACCOUNT_TOKEN_SECS);
+        *cached = Some((
+            token.clone(),
+            Instant::now() + Duration::from_secs(lifetime),
+        ));
+        Ok(token)
+    }
+}
+
+/// Google Gemini, authenticated with an API key or a service account
+struct GeminiClient {
+    auth: GeminiAuth,
+    base_url: String,
+    model: String,
+    transport: Transport,
+}
+
+impl GeminiClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let model = self.model.trim_start_matches("models/");
+        let endpoint = format!(
+            "{}/models/{}:batchEmbedContents",
+            self.base_url.trim_end_matches('/'),
+            model
+        );
+        let requests: Vec<Value> = input
+            .iter()
+            .map(|text| {
+                json!({
+                    "model": format!("models/{}", model),
+                    "content": { "parts": [{ "text": text }] },
+                })
+            })
+            .collect();
+        let token = match &self.auth {
+            GeminiAuth::ApiKey(_) => None,
+            GeminiAuth::ServiceAccount(account) => {
+                Some(account.access_token(&self.transport).await?)
+            }
+        };
+
+        let resp = self
+            .transport
+            .send(&endpoint, |client| {
+                let request = client
+                    .post(&endpoint)
+                    .json(&json!({ "requests": requests }));
+                match (&self.auth, &token) {
+                    (_, Some(token)) => request.bearer_auth(token),
+                    (GeminiAuth::ApiKey(key), None) => request.header("x-goog-api-key", key),
+                    (GeminiAuth::ServiceAccount(_), None) => request,
+                }
+            })
+            .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            // Gemini answers a bad API key with 400 API_KEY_INVALID
+            400 if body.contains("API_KEY_INVALID") => {
+                anyhow::bail!("Gemini rejected the API key (HTTP 400): {}", body)
+            }
+            401 | 403 => anyhow::bail!(
+                "Gemini rejected the credentials (HTTP {}): {}",
+                status,
+                body
+            ),
+            _ => anyhow::bail!("Gemini returned HTTP {}: {}", status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for GeminiClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let body = read_json(self.request(input).await?).await?;
+        body["embeddings"]
+            .as_array()
+            .context("Gemini response has no embeddings")?
+            .iter()
+            .map(|item| vector_from(&item["values"]))
+            .collect()
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// An Azure OpenAI deployment, authenticated with an `api-key` header
+struct AzureClient {
+    key: String,
+    endpoint: String,
+    deployment: String,
+    transport: Transport,
+}
+
+impl AzureClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let url = format!(
+            "{}/openai/deployments/{}/embeddings?api-version={}",
+            self.endpoint, self.deployment, AZURE_API_VERSION
+        );
+        let resp = self
+            .transport
+            .send(&url, |client| {
+                client
+                    .post(&url)
+                    .header("api-key", &self.key)
+                    .json(&json!({ "input": input }))
+            })
+            .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!(
+                "Azure OpenAI rejected the API key (HTTP {}): {}",
+                status,
+                body
+            ),
+            404 => anyhow::bail!(
+                "Azure OpenAI has no deployment {} at {} (HTTP 404): {}",
+                self.deployment,
+                self.endpoint,
+                body
+            ),
+            _ => anyhow::bail!("Azure OpenAI returned HTTP {}: {}", status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for AzureClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        parse_embeddings(self.request(input).await?).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// A self-hosted or other OpenAI-compatible endpoint, which may need extra
+/// headers besides the bearer key
+struct CustomClient {
+    key: String,
+    endpoint: String,
+    model: String,
+    headers: Vec<(String, String)>,
+    transport: Transport,
+}
+
+impl CustomClient {
+    async fn request(&self, input: &[String]) -> Result<reqwest::Response> {
+        let resp = post_embeddings(
+            &self.transport,
+            &self.endpoint,
+            &self.key,
+            &self.model,
+            input,
+            &self.headers,
+        )
+        .await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+
+        let body = error_body(resp).await;
+        match status.as_u16() {
+            401 | 403 => anyhow::bail!(
+                "{} rejected the request (HTTP {}), check the API key and extra headers: {}",
+                self.endpoint,
+                status,
+                body
+            ),
+            _ => anyhow::bail!("{} returned HTTP {}: {}", self.endpoint, status, body),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for CustomClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        parse_embeddings(self.request(input).await?).await
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.request(&probe()).await.map(|_| ())
+    }
+}
+
+/// A local Ollama server, which needs no key
+struct OllamaClient {
+    host: String,
+    model: String,
+    transport: Transport,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for OllamaClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.host);
+        let resp = self
+            .transport
+            .client()?
+            .post(&url)
+            .json(&json!({ "model": self.model, "input": input }))
+            .send()
+            .await
+            .with_context(|| format!("Could not reach Ollama at {}", self.host))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = error_body(resp).await;
+            anyhow::bail!("Ollama at {} returned HTTP {}: {}", self.host, status, body);
+        }
+
+        let body = read_json(resp).await.context("Invalid Ollama response")?;
+        body["embeddings"]
+            .as_array()
+            .context("Ollama response has no embeddings")?
+            .iter()
+            .map(vector_from)
+            .collect()
+    }
+
+    /// Confirm the server is reachable via its /api/tags endpoint
+    async fn validate(&self) -> Result<()> {
+        let url = format!("{}/api/tags", self.host);
+        let resp = self
+            .transport
+            .client()?
+            .get(&url)
+            .send()
+            .await
+            .with_context(|| format!("Could not reach Ollama at {}", self.host))?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Ollama at {} returned HTTP {}", self.host, resp.status());
+        }
+
+        Ok(())
+    }
+}
+
+/// The local ONNX model, run in-process from the directory given as the key
+struct LocalModelClient {
+    dir: std::path::PathBuf,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for LocalModelClient {
+    #[cfg(feature = "neural-onnx")]
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        use crate::neural::EmbeddingBackend;
+
+        let dir = self.dir.clone();
+        let input = input.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let (model, tokenizer) = crate::neural::local_model_files(&dir)?;
+            crate::neural::onnx::OnnxEmbedder::new(&model, &tokenizer)?.embed_batch(&input)
+        })
+        .await
+        .context("Local model task failed")?
+    }
+
+    #[cfg(not(feature = "neural-onnx"))]
+    async fn embed(&self, _input: &[String]) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!("Embedding with the local model requires the neural-onnx feature")
+    }
+
+    async fn validate(&self) -> Result<()> {
+        crate::neural::local_model_files(&self.dir).map(|_| ())
+    }
+}
+
+/// A provider missing a setting it can't be reached without; every call
+/// fails saying which
+struct UnconfiguredClient {
+    message: String,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingClient for UnconfiguredClient {
+    async fn embed(&self, _input: &[String]) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!("{}", self.message)
+    }
+
+    async fn validate(&self) -> Result<()> {
+        anyhow::bail!("{}", self.message)
+    }
+}
+
+/// In-memory client for tests: returns fixed-size vectors derived from the
+/// input text, or fails every call with a set error. Outside this crate's
+/// own tests it needs the `test-utils` feature.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug, Default)]
+pub struct MockEmbeddingClient {
+    dimension: usize,
+    error: Option<String>,
+    delay: Duration,
+    calls: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockEmbeddingClient {
+    /// A client that accepts every request
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            ..Self::default()
+        }
+    }
+
+    /// A client that fails every request with `message`
+    pub fn failing(message: &str) -> Self {
+        Self {
+            error: Some(message.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Make every call take `delay`, so overlapping calls can be observed
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Number of `embed` and `validate` calls made so far
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Most calls that were in progress at the same time
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    async fn record_call(&self) -> Result<()> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        match &self.error {
+            Some(message) => anyhow::bail!("{}", message),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+#[async_trait::async_trait]
+impl EmbeddingClient for MockEmbeddingClient {
+    async fn embed(&self, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.record_call().await?;
+        Ok(input
+            .iter()
+            .map(|text| {
+                (0..self.dimension)
+                    .map(|i| (text.len() + i) as f32)
+                    .collect()
+            })
+            .collect())
+    }
+
+    async fn validate(&self) -> Result<()> {
+        self.record_call().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::mock_http;
+
+    fn texts(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn voyage(server: &mock_http::MockServer) -> Box<dyn EmbeddingClient> {
+        let config = ClientConfig {
+            base_url: Some(format!("{}/v1", server.url)),
+            ..ClientConfig::default()
+        };
+        ApiProvider::Voyage.client("pa-test123", &config)
+    }
+
+    fn openai(server: &mock_http::MockServer) -> Box<dyn EmbeddingClient> {
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+        ApiProvider::OpenAI.client("sk-test123", &config)
+    }
+
+    #[tokio::test]
+    async fn test_voyage_validate_accepted() {
+        let server = mock_http::MockServer::start(vec![(200, r#"{"data":[]}"#)]);
+
+        voyage(&server).validate().await.unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v1/embeddings"));
+        assert!(requests[0].contains("voyage-code-2"));
+        assert!(requests[0].contains("Bearer pa-test123"));
+    }
+
+    #[tokio::test]
+    async fn test_voyage_validate_unauthorized() {
+        let server = mock_http::MockServer::start(vec![(401, r#"{"detail":"bad key"}"#)]);
+
+        let message = voyage(&server).validate().await.unwrap_err().to_string();
+        assert!(message.contains("rejected the API key"));
+        assert!(message.contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_voyage_retries_transient_errors() {
+        let server = mock_http::MockServer::start(vec![
+            (503, r#"{"detail":"overloaded"}"#),
+            (503, r#"{"detail":"overloaded"}"#),
+            (200, r#"{"data":[]}"#),
+        ]);
+
+        voyage(&server).validate().await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_openai_validate_accepted() {
+        let server = mock_http::MockServer::start(vec![(200, r#"{"data":[]}"#)]);
+
+        let config = ClientConfig {
+            base_url: Some(format!("{}/v1", server.url)),
+            ..ClientConfig::default()
+        };
+        ApiProvider::OpenAI
+            .client("sk-test123", &config)
+            .validate()
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v1/embeddings"));
+        assert!(requests[0].contains("text-embedding-3-small"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_validate_unauthorized() {
+        let server = mock_http::MockServer::start(vec![(401, r#"{"error":"invalid"}"#)]);
+
+        let err = openai(&server).validate().await.unwrap_err();
+        assert!(err.to_string().contains("invalid (HTTP 401)"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_validate_rate_limited() {
+        let server = mock_http::MockServer::start(vec![(429, r#"{"error":"slow down"}"#)]);
+
+        let err = openai(&server).validate().await.unwrap_err();
+        assert!(err.to_string().contains("rate limited"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_does_not_retry_unauthorized() {
+        let server = mock_http::MockServer::start(vec![
+            (401, r#"{"error":"invalid"}"#),
+            (200, r#"{"data":[]}"#),
+        ]);
+
+        assert!(openai(&server).validate().await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_openai_embed_parses_vectors() {
+        let server = mock_http::MockServer::start(vec![(
+            200,
+            r#"{"data":[{"embedding":[0.5,1.0]},{"embedding":[2.0,-1.5]}]}"#,
+        )]);
+
+        let vectors = openai(&server).embed(&texts(&["a", "b"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 1.0], vec![2.0, -1.5]]);
+        assert!(server.requests()[0].contains(r#""input":["a","b"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embed_parses_vectors() {
+        let server = mock_http::MockServer::start(vec![(200, r#"{"embeddings":[[1.0,2.0]]}"#)]);
+
+        let config = ClientConfig {
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+        let client = ApiProvider::Ollama.client(&server.url, &config);
+        let vectors = client.embed(&texts(&["fn main() {}"])).await.unwrap();
+
+        assert_eq!(vectors, vec![vec![1.0, 2.0]]);
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /api/embed"));
+        assert!(requests[0].contains("nomic-embed-text"));
+    }
+
+    #[test]
+    fn test_parse_extra_headers() {
+        let expected = vec![
+            ("X-Org-Id".to_string(), "acme".to_string()),
+            ("X-Team".to_string(), "search".to_string()),
+        ];
+        assert_eq!(
+            parse_extra_headers("X-Org-Id=acme; X-Team=search;").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_extra_headers(r#"{"X-Org-Id": "acme", "X-Team": "search"}"#).unwrap(),
+            expected
+        );
+        assert!(parse_extra_headers("").unwrap().is_empty());
+
+        assert!(parse_extra_headers("X-Org-Id").is_err());
+        assert!(parse_extra_headers("Bad Name=acme").is_err());
+        assert!(parse_extra_headers(r#"{"X-Org-Id": 42}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_sends_extra_headers() {
+        let server = mock_http::MockServer::start_requiring_header(
+            "X-Org-Id",
+            "acme",
+            vec![(200, r#"{"data":[{"embedding":[0.5,1.0]}]}"#)],
+        );
+        let mut config = ClientConfig {
+            base_url: Some(format!("{}/v1", server.url)),
+            model: Some("my-model".to_string()),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+
+        // Without the header the gateway turns the key away
+        let err = ApiProvider::Custom
+            .client("secret-key", &config)
+            .validate()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("HTTP 400"));
+
+        config.headers = parse_extra_headers("X-Org-Id=acme").unwrap();
+        let client = ApiProvider::Custom.client("secret-key", &config);
+        client.validate().await.unwrap();
+        let vectors = client.embed(&texts(&["a"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 1.0]]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].starts_with("POST /v1/embeddings"));
+        assert!(requests[2].contains("Bearer secret-key"));
+        assert!(requests[2].contains("my-model"));
+    }
+
+    #[tokio::test]
+    async fn test_local_model_validate_checks_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_string_lossy().to_string();
+        let client = ApiProvider::LocalOnnx.client(&dir, &ClientConfig::default());
+
+        let err = client.validate().await.unwrap_err();
+        assert!(err.to_string().contains("model.onnx"));
+
+        std::fs::write(temp.path().join("model.onnx"), b"").unwrap();
+        std::fs::write(temp.path().join("tokenizer.json"), b"{}").unwrap();
+        client.validate().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cohere_embed_parses_vectors() {
+        let server = mock_http::MockServer::start(vec![(200, r#"{"embeddings":[[0.5,1.0]]}"#)]);
+        let config = ClientConfig {
+            base_url: Some(format!("{}/v1", server.url)),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+
+        let client = ApiProvider::Cohere.client("cohere-key", &config);
+        let vectors = client.embed(&texts(&["a"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 1.0]]);
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /v1/embed "));
+        assert!(requests[0].contains("Bearer cohere-key"));
+        assert!(requests[0].contains(r#""texts":["a"]"#));
+        assert!(requests[0].contains("embed-english-v3.0"));
+    }
+
+    #[tokio::test]
+    async fn test_cohere_validate_unauthorized() {
+        let server =
+            mock_http::MockServer::start(vec![(401, r#"{"message":"invalid api token"}"#)]);
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            ..ClientConfig::default()
+        };
+        let err = ApiProvider::Cohere
+            .client("cohere-key", &config)
+            .validate()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Cohere rejected the API key"));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_embed_with_api_key() {
+        let server =
+            mock_http::MockServer::start(vec![(200, r#"{"embeddings":[{"values":[0.5,1.0]}]}"#)]);
+        let config = ClientConfig {
+            base_url: Some(format!("{}/v1beta", server.url)),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+
+        let client = ApiProvider::Gemini.client("AIzaSyTest", &config);
+        let vectors = client.embed(&texts(&["a"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 1.0]]);
+
+        let requests = server.requests();
+        assert!(
+            requests[0].starts_with("POST /v1beta/models/text-embedding-004:batchEmbedContents ")
+        );
+        assert!(requests[0].contains("x-goog-api-key: AIzaSyTest"));
+        assert!(requests[0].contains(r#""model":"models/text-embedding-004""#));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_rejects_invalid_api_key() {
+        let server = mock_http::MockServer::start(vec![(
+            400,
+            r#"{"error":{"status":"INVALID_ARGUMENT","details":[{"reason":"API_KEY_INVALID"}]}}"#,
+        )]);
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            ..ClientConfig::default()
+        };
+        let err = ApiProvider::Gemini
+            .client("AIzaSyTest", &config)
+            .validate()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Gemini rejected the API key"));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_service_account_exchanges_token() {
+        let server = mock_http::MockServer::start(vec![
+            (200, r#"{"access_token":"ya29.test","expires_in":3600}"#),
+            (200, r#"{"embeddings":[{"values":[0.5,1.0]}]}"#),
+        ]);
+        let temp = tempfile::tempdir().unwrap();
+        let key_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/test_service_account_key.pem"
+        );
+        let account = json!({
+            "type": "service_account",
+            "project_id": "narsil-test",
+            "private_key": std::fs::read_to_string(key_path).unwrap(),
+            "client_email": "indexer@narsil-test.iam.gserviceaccount.com",
+            "token_uri": format!("{}/token", server.url),
+        });
+        let path = temp.path().join("service-account.json");
+        std::fs::write(&path, account.to_string()).unwrap();
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+
+        let client = ApiProvider::Gemini.client(&path.display().to_string(), &config);
+        client.embed(&texts(&["a"])).await.unwrap();
+        client.embed(&texts(&["b"])).await.unwrap();
+
+        // The token is fetched once and reused
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("POST /token "));
+        assert!(requests[0]
+            .contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer"));
+        assert!(requests[1].contains("Bearer ya29.test"));
+        assert!(requests[2].contains("Bearer ya29.test"));
+        assert!(!requests[1].contains("x-goog-api-key"));
+    }
+
+    #[tokio::test]
+    async fn test_azure_embed_uses_deployment() {
+        let server =
+            mock_http::MockServer::start(vec![(200, r#"{"data":[{"embedding":[0.5,1.0]}]}"#)]);
+        let config = ClientConfig {
+            base_url: Some(format!("{}/", server.url)),
+            model: Some("code-embeddings".to_string()),
+            dimension: Some(2),
+            ..ClientConfig::default()
+        };
+
+        let client = ApiProvider::AzureOpenAI.client("azure-key", &config);
+        let vectors = client.embed(&texts(&["a"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 1.0]]);
+
+        let requests = server.requests();
+        assert!(requests[0].starts_with(&format!(
+            "POST /openai/deployments/code-embeddings/embeddings?api-version={} ",
+            AZURE_API_VERSION
+        )));
+        assert!(requests[0].contains("api-key: azure-key"));
+        assert!(!requests[0].contains("Bearer"));
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_providers_fail() {
+        let err = ApiProvider::AzureOpenAI
+            .client("azure-key", &ClientConfig::default())
+            .validate()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(AZURE_OPENAI_ENDPOINT_VAR));
+
+        let client = ApiProvider::Custom.client("key", &ClientConfig::default());
+        let err = client.embed(&texts(&["a"])).await.unwrap_err();
+        assert!(err.to_string().contains(EMBEDDING_BASE_URL_VAR));
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint_used_as_is() {
+        let server =
+            mock_http::MockServer::start(vec![(200, r#"{"data":[{"embedding":[0.5,1.0]}]}"#)]);
+        let config = ClientConfig {
+            endpoint: Some(format!("{}/embed/v2", server.url)),
+            model: Some("local-model".to_string()),
+            ..ClientConfig::default()
+        };
+
+        // No key: no Authorization header at all
+        ApiProvider::Custom
+            .client("", &config)
+            .embed(&texts(&["a"]))
+            .await
+            .unwrap();
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /embed/v2 "));
+        assert!(!requests[0].to_lowercase().contains("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_client() {
+        let client = MockEmbeddingClient::new(3);
+        let vectors = client.embed(&texts(&["ab", "c"])).await.unwrap();
+        assert_eq!(vectors, vec![vec![2.0, 3.0, 4.0], vec![1.0, 2.0, 3.0]]);
+        client.validate().await.unwrap();
+        assert_eq!(client.calls(), 2);
+
+        let failing = MockEmbeddingClient::failing("key revoked");
+        let err = failing.validate().await.unwrap_err();
+        assert_eq!(err.to_string(), "key revoked");
+    }
+
+    fn counting_client(
+        size: usize,
+        max_tokens: usize,
+    ) -> (Arc<MockEmbeddingClient>, BatchedClient) {
+        let mock = Arc::new(MockEmbeddingClient::new(2));
+        let client = BatchedClient::new(Box::new(Arc::clone(&mock)), size, max_tokens);
+        (mock, client)
+    }
+
+    #[tokio::test]
+    async fn test_batched_client_request_count() {
+        let input: Vec<String> = (0..250).map(|i| format!("fn f{}() {{}}", i)).collect();
+
+        for (size, requests) in [(96, 3), (100, 3), (250, 1), (1, 250)] {
+            let (mock, client) = counting_client(size, usize::MAX);
+            let vectors = client.embed(&input).await.unwrap();
+            assert_eq!(vectors.len(), 250);
+            assert_eq!(mock.calls(), requests, "batch size {}", size);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batched_client_keeps_input_order() {
+        let (_, client) = counting_client(2, usize::MAX);
+        let vectors = client.embed(&texts(&["a", "bbb", "cc"])).await.unwrap();
+        let firsts: Vec<f32> = vectors.iter().map(|v| v[0]).collect();
+        assert_eq!(firsts, vec![1.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_split_batches_by_tokens() {
+        // 400 characters is about 100 tokens
+        let input: Vec<String> = (0..5).map(|_| "x".repeat(400)).collect();
+        let sizes: Vec<usize> = split_batches(&input, 10, 250)
+            .iter()
+            .map(|b| b.len())
+            .collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+
+        // An input over the limit on its own still gets sent, alone
+        let input = vec!["x".repeat(40), "x".repeat(4000), "x".repeat(40)];
+        let sizes: Vec<usize> = split_batches(&input, 10, 250)
+            .iter()
+            .map(|b| b.len())
+            .collect();
+        assert_eq!(sizes, vec![1, 1, 1]);
+
+        assert!(split_batches(&[], 10, 250).is_empty());
+    }
+
+    #[test]
+    fn test_batch_limits_size() {
+        let voyage = ApiProvider::Voyage.batch_limits();
+        assert_eq!(voyage.size(None), 96);
+        assert_eq!(voyage.size(Some(64)), 64);
+        assert_eq!(voyage.size(Some(1000)), 128);
+        assert_eq!(voyage.size(Some(0)), 1);
+        assert_eq!(ApiProvider::OpenAI.batch_limits().size(None), 2048);
+    }
+
+    #[tokio::test]
+    async fn test_provider_client_batches_requests() {
+        let server = mock_http::MockServer::start(vec![(
+            200,
+            r#"{"data":[{"embedding":[1.0]},{"embedding":[2.0]}]}"#,
+        )]);
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            batch_size: Some(2),
+            dimension: Some(1),
+            ..ClientConfig::default()
+        };
+
+        let client = ApiProvider::OpenAI.client("sk-test123", &config);
+        let vectors = client.embed(&texts(&["a", "b", "c", "d"])).await.unwrap();
+
+        assert_eq!(vectors.len(), 4);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_client_caps_concurrency() {
+        let mock = Arc::new(MockEmbeddingClient::new(2).with_delay(Duration::from_millis(20)));
+        let client = Arc::new(RateLimitedClient::new(Box::new(Arc::clone(&mock)), 3, None));
+
+        let tasks: Vec<_> = (0..12)
+            .map(|i| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move { client.embed(&[format!("input {}", i)]).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(mock.calls(), 12);
+        assert!(mock.max_in_flight() <= 3);
+        assert!(mock.max_in_flight() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_spaces_requests() {
+        // 600 a minute is one every 100ms once the initial burst is used up
+        let bucket = TokenBucket::new(600);
+        for _ in 0..600 {
+            bucket.acquire().await;
+        }
+
+        let start = Instant::now();
+        bucket.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_rate_limits_are_conservative() {
+        for provider in ApiProvider::ALL {
+            let limits = provider.rate_limits();
+            assert!((1..=4).contains(&limits.max_concurrency), "{:?}", provider);
+        }
+        assert_eq!(ApiProvider::Ollama.rate_limits().max_concurrency, 1);
+    }
+
+    #[test]
+    fn test_model_dimensions() {
+        assert_eq!(model_dimension("voyage-code-2"), Some(1536));
+        assert_eq!(model_dimension("voyage-3"), Some(1024));
+        assert_eq!(model_dimension("text-embedding-3-small"), Some(1536));
+        assert_eq!(model_dimension("text-embedding-3-large"), Some(3072));
+        assert_eq!(model_dimension("text-embedding-ada-002"), Some(1536));
+        assert_eq!(model_dimension("embed-english-v3.0"), Some(1024));
+        assert_eq!(model_dimension("text-embedding-004"), Some(768));
+        assert_eq!(model_dimension("nomic-embed-text"), Some(768));
+        assert_eq!(model_dimension("my-finetune"), None);
+    }
+
+    #[test]
+    fn test_every_offered_model_has_a_dimension() {
+        for provider in ApiProvider::ALL {
+            for model in provider.models() {
+                assert!(
+                    provider.dimension(model).is_some(),
+                    "{} {}",
+                    provider.id(),
+                    model
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_provider_dimension() {
+        assert_eq!(
+            ApiProvider::OpenAI.dimension("text-embedding-3-large"),
+            Some(3072)
+        );
+        // A model from another provider isn't served by this one
+        assert_eq!(ApiProvider::OpenAI.dimension("voyage-code-2"), None);
+        // Custom endpoints can serve any known model
+        assert_eq!(ApiProvider::Custom.dimension("nomic-embed-text"), Some(768));
+        assert_eq!(ApiProvider::Custom.dimension("my-finetune"), None);
+    }
+
+    #[tokio::test]
+    async fn test_batched_client_rejects_wrong_dimension() {
+        let client = BatchedClient::new(Box::new(MockEmbeddingClient::new(3)), 10, usize::MAX)
+            .expect_dimension(Some(4));
+        let err = client.embed(&texts(&["a"])).await.unwrap_err().to_string();
+        assert!(err.contains("3-dimensional"));
+        assert!(err.contains("expected 4"));
+        assert!(err.contains(EMBEDDING_DIMENSION_VAR));
+
+        let client = BatchedClient::new(Box::new(MockEmbeddingClient::new(4)), 10, usize::MAX)
+            .expect_dimension(Some(4));
+        assert!(client.embed(&texts(&["a"])).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_truncating_client_trims_to_limit() {
+        // The mock's first component is the length of the text it was given
+        let long = "x".repeat(1000);
+        for strategy in [Truncation::Head, Truncation::HeadTail] {
+            let client =
+                TruncatingClient::new(Box::new(MockEmbeddingClient::new(1)), 100, strategy);
+            let vectors = client
+                .embed(&[long.clone(), "short".to_string()])
+                .await
+                .unwrap();
+            assert!(vectors[0][0] <= 100.0, "{:?}", strategy);
+            assert!(vectors[0][0] >= 90.0, "{:?}", strategy);
+            assert_eq!(vectors[1][0], 5.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncating_client_split_average() {
+        let mock = Arc::new(MockEmbeddingClient::new(1));
+        let client =
+            TruncatingClient::new(Box::new(Arc::clone(&mock)), 100, Truncation::SplitAverage);
+
+        let vectors = client.embed(&["x".repeat(250)]).await.unwrap();
+        // Pieces of 100, 100 and 50 bytes, averaged
+        assert_eq!(vectors.len(), 1);
+        assert!((vectors[0][0] - 250.0 / 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_shorten() {
+        let text = format!("use std::fs;\n{}\nfn last() {{}}", "body ".repeat(100));
+
+        let head = shorten(&text, 40, Truncation::Head);
+        assert_eq!(head, vec![text[..40].to_string()]);
+
+        let head_tail = &shorten(&text, 40, Truncation::HeadTail)[0];
+        assert!(head_tail.len() <= 40);
+        assert!(head_tail.starts_with("use std::fs;"));
+        assert!(head_tail.ends_with("fn last() {}"));
+
+        let pieces = shorten(&text, 40, Truncation::SplitAverage);
+        assert!(pieces.iter().all(|p| p.len() <= 40));
+        assert_eq!(pieces.concat(), text);
+
+        // Multi-byte characters are never cut in half
+        let wide = "é".repeat(50);
+        assert!(shorten(&wide, 15, Truncation::Head)[0].len() <= 15);
+        assert_eq!(shorten(&wide, 15, Truncation::SplitAverage).concat(), wide);
+
+        assert_eq!(shorten("short", 40, Truncation::Head), vec!["short"]);
+    }
+
+    #[test]
+    fn test_truncation_parse() {
+        assert_eq!(Truncation::parse("truncate"), Some(Truncation::Head));
+        assert_eq!(Truncation::parse("Head-Tail"), Some(Truncation::HeadTail));
+        assert_eq!(Truncation::parse("split"), Some(Truncation::SplitAverage));
+        assert_eq!(Truncation::parse("middle"), None);
+    }
+
+    #[test]
+    fn test_model_max_tokens() {
+        assert_eq!(model_max_tokens("text-embedding-3-small"), Some(8_191));
+        assert_eq!(model_max_tokens("voyage-code-2"), Some(16_000));
+        assert_eq!(model_max_tokens("my-finetune"), None);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        // A plain HTTP proxy is sent the absolute target URL
+        let proxy = mock_http::MockServer::start(vec![(200, r#"{"data":[]}"#)]);
+        let config = ClientConfig {
+            base_url: Some("http://embeddings.example.invalid/v1".to_string()),
+            proxy: Some(proxy.url.clone()),
+            ..ClientConfig::default()
+        };
+
+        ApiProvider::OpenAI
+            .client("sk-test123", &config)
+            .validate()
+            .await
+            .unwrap();
+
+        let requests = proxy.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST http://embeddings.example.invalid/v1/embeddings"));
+    }
+
+    #[test]
+    fn test_invalid_proxy_url() {
+        assert!(proxy("not a url").is_err());
+        assert!(proxy("http://proxy.internal:3128").is_ok());
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles() {
+        let first = backoff_delay(1);
+        let second = backoff_delay(2);
+        assert!(first >= Duration::from_millis(200) && first < Duration::from_millis(300));
+        assert!(second >= Duration::from_millis(400) && second < Duration::from_millis(500));
+    }
+}
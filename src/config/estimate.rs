@@ -0,0 +1,185 @@
+//! Token and cost estimates for a neural index run
+//!
+//! Counts use the same rough tokenizer as the embedding client (about four
+//! characters per token), so they are estimates rather than exact figures.
+
+use std::path::{Path, PathBuf};
+
+use super::embedding_client::CHARS_PER_TOKEN;
+
+/// Estimated cost above which the wizard asks before enabling embeddings
+pub const COST_CONFIRM_THRESHOLD_USD: f64 = 5.0;
+
+/// How many directory levels below a repo root are searched for source
+/// files when checking that the repo is worth indexing
+pub const REPO_CHECK_DEPTH: usize = 3;
+
+/// Size of the code that a neural index would send for embedding
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IndexEstimate {
+    pub files: usize,
+    pub bytes: u64,
+    pub tokens: u64,
+}
+
+impl IndexEstimate {
+    /// Add up the sizes of `files`, skipping any that can't be read
+    pub fn from_files(files: &[PathBuf]) -> Self {
+        let bytes: u64 = files
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .sum();
+        Self {
+            files: files.len(),
+            bytes,
+            tokens: estimate_tokens(bytes),
+        }
+    }
+
+    /// Estimated cost in US dollars at `price_per_1k` dollars per 1,000 tokens
+    pub fn cost(&self, price_per_1k: f64) -> f64 {
+        estimate_cost(self.tokens, price_per_1k)
+    }
+}
+
+/// Estimated token count of `bytes` of source text
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes.div_ceil(CHARS_PER_TOKEN as u64)
+}
+
+/// Cost in US dollars of `tokens` at `price_per_1k` dollars per 1,000 tokens
+pub fn estimate_cost(tokens: u64, price_per_1k: f64) -> f64 {
+    tokens as f64 / 1000.0 * price_per_1k
+}
+
+/// List price in US dollars per 1,000 tokens for a known embedding model.
+///
+/// Local and free-tier models cost nothing; `None` means the price isn't
+/// known, as for custom endpoints and Azure deployments.
+pub fn price_per_1k_tokens(model: &str) -> Option<f64> {
+    let price = match model {
+        "voyage-code-2" | "voyage-large-2" => 0.000_12,
+        "voyage-code-3" | "voyage-3-large" => 0.000_18,
+        "voyage-3" => 0.000_06,
+        "voyage-3-lite" => 0.000_02,
+        "text-embedding-3-small" => 0.000_02,
+        "text-embedding-3-large" => 0.000_13,
+        "text-embedding-ada-002" => 0.000_1,
+        "embed-english-v3.0" | "embed-multilingual-v3.0" => 0.000_1,
+        "text-embedding-004" => 0.0,
+        "nomic-embed-text" | "mxbai-embed-large" | "all-minilm" => 0.0,
+        "all-MiniLM-L6-v2" => 0.0,
+        _ => return None,
+    };
+    Some(price)
+}
+
+/// Files the indexer would parse under `repo`, honouring .gitignore
+pub fn embeddable_files(repo: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    ignore::WalkBuilder::new(repo)
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| has_extension(path, extensions))
+        .collect()
+}
+
+/// Whether `repo` has a file the indexer would parse within `max_depth`
+/// levels of its root, honouring .gitignore
+pub fn has_source_files(repo: &Path, extensions: &[&str], max_depth: usize) -> bool {
+    ignore::WalkBuilder::new(repo)
+        .max_depth(Some(max_depth))
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .any(|entry| has_extension(entry.path(), extensions))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext))
+}
+
+/// Estimate for every parseable file in `repos`
+pub fn estimate_repos(repos: &[PathBuf]) -> anyhow::Result<IndexEstimate> {
+    let parser = crate::parser::LanguageParser::new()?;
+    let extensions = parser.supported_extensions();
+    let files: Vec<PathBuf> = repos
+        .iter()
+        .flat_map(|repo| embeddable_files(repo, &extensions))
+        .collect();
+    Ok(IndexEstimate::from_files(&files))
+}
+
+/// Format a dollar amount, with more precision for small sums
+pub fn format_cost(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        format!("${:.4}", cost)
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(0), 0);
+        assert_eq!(estimate_tokens(4), 1);
+        assert_eq!(estimate_tokens(5), 2);
+        assert_eq!(estimate_tokens(4_000_000), 1_000_000);
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        // 1M tokens of text-embedding-3-small at $0.02 per million
+        let price = price_per_1k_tokens("text-embedding-3-small").unwrap();
+        assert!((estimate_cost(1_000_000, price) - 0.02).abs() < 1e-9);
+
+        // A 2GB monorepo with voyage-code-2
+        let estimate = IndexEstimate {
+            files: 100_000,
+            bytes: 2_000_000_000,
+            tokens: estimate_tokens(2_000_000_000),
+        };
+        let cost = estimate.cost(price_per_1k_tokens("voyage-code-2").unwrap());
+        assert!((cost - 60.0).abs() < 1e-6);
+
+        assert_eq!(estimate_cost(1_000_000, 0.0), 0.0);
+        assert_eq!(price_per_1k_tokens("my-finetune"), None);
+    }
+
+    #[test]
+    fn test_estimate_from_files() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("main.rs"), "x".repeat(400)).unwrap();
+        std::fs::write(temp.path().join("lib.rs"), "x".repeat(100)).unwrap();
+        std::fs::write(temp.path().join("notes.txt"), "x".repeat(1000)).unwrap();
+
+        let files = embeddable_files(temp.path(), &["rs"]);
+        let estimate = IndexEstimate::from_files(&files);
+        assert_eq!(estimate.files, 2);
+        assert_eq!(estimate.bytes, 500);
+        assert_eq!(estimate.tokens, 125);
+    }
+
+    #[test]
+    fn test_format_cost() {
+        assert_eq!(format_cost(0.0), "$0.00");
+        assert_eq!(format_cost(0.0025), "$0.0025");
+        assert_eq!(format_cost(60.0), "$60.00");
+    }
+}
@@ -0,0 +1,115 @@
+//! Minimal HTTP server returning canned responses, for validation tests
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Serve one canned `(status, body)` response per incoming connection.
+    /// The last response is repeated once the list runs out.
+    pub fn start(responses: Vec<(u16, &'static str)>) -> Self {
+        Self::serve(responses, None, Duration::ZERO)
+    }
+
+    /// Like [`start`](Self::start), but wait `delay` before each response
+    pub fn start_delayed(delay: Duration, responses: Vec<(u16, &'static str)>) -> Self {
+        Self::serve(responses, None, delay)
+    }
+
+    /// Like [`start`](Self::start), but answer 400 to any request without
+    /// the header `name: value`
+    pub fn start_requiring_header(
+        name: &'static str,
+        value: &'static str,
+        responses: Vec<(u16, &'static str)>,
+    ) -> Self {
+        Self::serve(responses, Some((name, value)), Duration::ZERO)
+    }
+
+    fn serve(
+        responses: Vec<(u16, &'static str)>,
+        required: Option<(&'static str, &'static str)>,
+        delay: Duration,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else { break };
+                let request = read_request(&mut stream);
+                let accepted =
+                    required.is_none_or(|(name, value)| has_header(&request, name, value));
+                seen.lock().unwrap().push(request);
+                thread::sleep(delay);
+
+                let (status, body) = if accepted {
+                    responses[i.min(responses.len() - 1)]
+                } else {
+                    (400, r#"{"error":"missing required header"}"#)
+                };
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Raw requests received so far (request line, headers and body)
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn has_header(request: &str, name: &str, value: &str) -> bool {
+    request
+        .split("\r\n\r\n")
+        .next()
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .any(|(n, v)| n.eq_ignore_ascii_case(name) && v.trim() == value)
+}
+
+fn read_request(stream: &mut impl Read) -> String {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+
+        let text = String::from_utf8_lossy(&data);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|l| {
+                    let (name, value) = l.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if data.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&data).into_owned()
+}
@@ -14,10 +14,16 @@
 /// 5. Default config (built-in)
 pub mod cli;
 pub mod editor;
+pub mod embedding_client;
+pub mod estimate;
 pub mod filter;
 pub mod loader;
+#[cfg(test)]
+pub(crate) mod mock_http;
+pub mod output;
 pub mod preset;
 pub mod schema;
+pub mod secrets;
 pub mod validation;
 pub mod wizard;
 
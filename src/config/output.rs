@@ -0,0 +1,84 @@
+/// Status markers for config command output
+///
+/// Emoji markers garble CI logs and terminals without UTF-8 support, so they
+/// fall back to ASCII when `--no-color` is given or `NO_COLOR` is set.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--no-color`; `NO_COLOR` is checked separately on every call
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Outcome shown in front of a status message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Fail,
+    Warn,
+}
+
+impl Status {
+    /// The marker for this status, ASCII when `plain` is set
+    pub fn marker(self, plain: bool) -> &'static str {
+        match (self, plain) {
+            (Status::Ok, false) => "✅",
+            (Status::Fail, false) => "❌",
+            // The warning sign renders one column wide in most terminals
+            (Status::Warn, false) => "⚠️ ",
+            (Status::Ok, true) => "[ok]",
+            (Status::Fail, true) => "[fail]",
+            (Status::Warn, true) => "[warn]",
+        }
+    }
+}
+
+/// Force ASCII markers, as for `--no-color`
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+/// Whether ASCII markers are in use
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed) || no_color_env(std::env::var_os("NO_COLOR"))
+}
+
+/// `NO_COLOR` counts when set to any non-empty value (see no-color.org)
+fn no_color_env(value: Option<std::ffi::OsString>) -> bool {
+    value.is_some_and(|v| !v.is_empty())
+}
+
+/// The marker for `status` in the current output mode
+pub fn marker(status: Status) -> &'static str {
+    status.marker(is_plain())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_markers_are_ascii() {
+        assert_eq!(Status::Ok.marker(true), "[ok]");
+        assert_eq!(Status::Fail.marker(true), "[fail]");
+        assert_eq!(Status::Warn.marker(true), "[warn]");
+    }
+
+    #[test]
+    fn test_default_markers_are_emoji() {
+        assert_eq!(Status::Ok.marker(false), "✅");
+        assert_eq!(Status::Fail.marker(false), "❌");
+        assert!(Status::Warn.marker(false).starts_with("⚠️"));
+    }
+
+    #[test]
+    fn test_set_plain() {
+        set_plain(true);
+        assert_eq!(marker(Status::Ok), "[ok]");
+        set_plain(false);
+    }
+
+    #[test]
+    fn test_no_color_env() {
+        assert!(no_color_env(Some("1".into())));
+        assert!(!no_color_env(Some("".into())));
+        assert!(!no_color_env(None));
+    }
+}
@@ -0,0 +1,177 @@
+/// Keyring-backed storage for embedding API keys
+///
+/// Instead of writing a key into an editor config in plaintext, the wizard
+/// can store it in the OS keyring and write a reference such as
+/// `keyring:narsil-mcp/voyage`. [`resolve_secret`] turns such a reference
+/// back into the key when the server starts.
+#[cfg(feature = "keyring")]
+use anyhow::Context;
+use anyhow::Result;
+use std::fmt;
+
+/// Keyring service name under which API keys are stored
+pub const KEYRING_SERVICE: &str = "narsil-mcp";
+/// Prefix marking a config value as a keyring reference
+pub const KEYRING_PREFIX: &str = "keyring:";
+
+/// A place to store secrets by service and account name
+pub trait SecretStore: fmt::Debug + Send + Sync {
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()>;
+    fn get(&self, service: &str, account: &str) -> Result<String>;
+}
+
+/// The OS keyring (Keychain, Credential Manager or Secret Service)
+#[cfg(feature = "keyring")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemKeyring;
+
+#[cfg(feature = "keyring")]
+impl SecretStore for SystemKeyring {
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+        keyring::Entry::new(service, account)
+            .and_then(|entry| entry.set_password(secret))
+            .context("Failed to store secret in the system keyring")
+    }
+
+    fn get(&self, service: &str, account: &str) -> Result<String> {
+        keyring::Entry::new(service, account)
+            .and_then(|entry| entry.get_password())
+            .with_context(|| {
+                format!(
+                    "Failed to read {}/{} from the system keyring",
+                    service, account
+                )
+            })
+    }
+}
+
+/// Build the config value that points at a stored secret
+pub fn keyring_reference(account: &str) -> String {
+    format!("{}{}/{}", KEYRING_PREFIX, KEYRING_SERVICE, account)
+}
+
+/// Split a `keyring:<service>/<account>` reference, or `None` for plain values
+pub fn parse_keyring_reference(value: &str) -> Option<(&str, &str)> {
+    value
+        .strip_prefix(KEYRING_PREFIX)?
+        .split_once('/')
+        .filter(|(service, account)| !service.is_empty() && !account.is_empty())
+}
+
+/// Resolve a config value that may be a keyring reference.
+///
+/// Plain values are returned unchanged.
+pub fn resolve_secret(value: &str) -> Result<String> {
+    if parse_keyring_reference(value).is_none() {
+        return Ok(value.to_string());
+    }
+
+    #[cfg(feature = "keyring")]
+    {
+        resolve_secret_with(&SystemKeyring, value)
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    {
+        anyhow::bail!(
+            "{} is a keyring reference but narsil-mcp was built without the `keyring` feature",
+            value
+        )
+    }
+}
+
+/// [`resolve_secret`] against an explicit store
+pub fn resolve_secret_with(store: &dyn SecretStore, value: &str) -> Result<String> {
+    match parse_keyring_reference(value) {
+        Some((service, account)) => store.get(service, account),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// In-memory store standing in for the OS keyring in tests
+#[cfg(feature = "keyring")]
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    secrets: std::sync::Mutex<std::collections::HashMap<(String, String), String>>,
+    /// Fail every call, as when no keyring backend is available
+    unavailable: bool,
+}
+
+#[cfg(feature = "keyring")]
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store that rejects every operation
+    pub fn unavailable() -> Self {
+        Self {
+            unavailable: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl SecretStore for MemoryStore {
+    fn set(&self, service: &str, account: &str, secret: &str) -> Result<()> {
+        if self.unavailable {
+            anyhow::bail!("No keyring backend available");
+        }
+        self.secrets.lock().unwrap().insert(
+            (service.to_string(), account.to_string()),
+            secret.to_string(),
+        );
+        Ok(())
+    }
+
+    fn get(&self, service: &str, account: &str) -> Result<String> {
+        if self.unavailable {
+            anyhow::bail!("No keyring backend available");
+        }
+        self.secrets
+            .lock()
+            .unwrap()
+            .get(&(service.to_string(), account.to_string()))
+            .cloned()
+            .with_context(|| format!("No secret stored for {}/{}", service, account))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_reference_round_trip() {
+        let reference = keyring_reference("voyage");
+        assert_eq!(reference, "keyring:narsil-mcp/voyage");
+        assert_eq!(
+            parse_keyring_reference(&reference),
+            Some(("narsil-mcp", "voyage"))
+        );
+    }
+
+    #[test]
+    fn test_plain_values_are_not_references() {
+        assert_eq!(parse_keyring_reference("pa-abc123"), None);
+        assert_eq!(parse_keyring_reference("keyring:"), None);
+        assert_eq!(parse_keyring_reference("keyring:/voyage"), None);
+        assert_eq!(resolve_secret("pa-abc123").unwrap(), "pa-abc123");
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_resolve_secret_with_store() {
+        let store = MemoryStore::new();
+        store
+            .set(KEYRING_SERVICE, "voyage", "pa-secret123")
+            .unwrap();
+
+        assert_eq!(
+            resolve_secret_with(&store, "keyring:narsil-mcp/voyage").unwrap(),
+            "pa-secret123"
+        );
+        assert!(resolve_secret_with(&store, "keyring:narsil-mcp/openai").is_err());
+    }
+}
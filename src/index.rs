@@ -174,7 +174,11 @@ impl CodeIntelEngine {
         };
         // Initialize neural engine if enabled
         let neural_engine = if options.neural_config.enabled {
-            match NeuralEngine::new(options.neural_config.clone()) {
+            let mut neural_config = options.neural_config.clone();
+            if let Some(dir) = &neural_config.cache_dir {
+                neural_config.cache_dir = Some(expand_path(dir)?);
+            }
+            match NeuralEngine::new(neural_config) {
                 Ok(engine) => {
                     info!(
                         "Neural embedding engine initialized (backend={}, model={:?})",
@@ -115,18 +115,25 @@ struct ServerArgs {
     #[arg(long)]
     remote: bool,
 
-    /// Enable neural embeddings for semantic search (requires EMBEDDING_API_KEY, VOYAGE_API_KEY, or OPENAI_API_KEY)
+    /// Enable neural embeddings for semantic search (requires EMBEDDING_API_KEY, VOYAGE_API_KEY, or OPENAI_API_KEY;
+    /// without one, builds with the neural-onnx feature use the local model in EMBEDDING_LOCAL_MODEL)
     #[arg(long)]
     neural: bool,
 
-    /// Neural embedding backend: "api" (default) or "onnx"
+    /// Neural embedding backend: "api" (default), "local" or "onnx"
     #[arg(long, default_value = "api")]
     neural_backend: String,
 
-    /// Neural embedding model name (e.g., "voyage-code-2", "text-embedding-3-small")
+    /// Neural embedding model name (e.g., "voyage-code-2", "text-embedding-3-small").
+    /// Defaults to EMBEDDING_MODEL if set
     #[arg(long)]
     neural_model: Option<String>,
 
+    /// Don't cache neural embeddings on disk (by default they are kept in
+    /// <index-path>/embeddings and reused for unchanged code)
+    #[arg(long)]
+    no_cache: bool,
+
     /// Enable HTTP server for visualization frontend
     #[arg(long)]
     http: bool,
@@ -217,16 +224,33 @@ async fn main() -> Result<()> {
     }
 
     // Build neural config
+    // Fall back to the model recorded by `config init --neural`
+    let model_name = server_args
+        .neural_model
+        .clone()
+        .or_else(|| std::env::var("EMBEDDING_MODEL").ok());
+    let defaults = neural::NeuralConfig::default();
+    // EMBEDDING_DIMENSION covers models the lookup doesn't know
+    let dimension = config::embedding_client::dimension_from_env()
+        .or_else(|| {
+            model_name
+                .as_deref()
+                .or(defaults.model_name.as_deref())
+                .and_then(config::embedding_client::model_dimension)
+        })
+        .unwrap_or(defaults.dimension);
     let neural_config = neural::NeuralConfig {
         enabled: server_args.neural,
         backend: server_args.neural_backend.clone(),
-        model_name: server_args.neural_model.clone(),
-        ..Default::default()
+        model_name,
+        dimension,
+        cache_dir: (!server_args.no_cache).then(|| server_args.index_path.join("embeddings")),
+        ..defaults
     };
     if server_args.neural {
         info!(
             "Neural embeddings requested (backend={}, model={:?})",
-            server_args.neural_backend, server_args.neural_model
+            server_args.neural_backend, neural_config.model_name
         );
     }
 
@@ -2,7 +2,9 @@
 //!
 //! Supports multiple backends:
 //! - ONNX models (CodeBERT, StarEncoder, etc.) - requires `neural` feature
-//! - API-based (Voyage, OpenAI) for higher quality
+//! - API-based (Voyage, OpenAI, Cohere, Gemini, Azure OpenAI) for higher quality
+//! - A local ONNX model (all-MiniLM) that needs no key or network, used
+//!   when no API is configured - requires `neural-onnx` feature
 //!
 //! This module provides dense vector embeddings for semantic code search,
 //! complementing the TF-IDF embeddings in embeddings.rs
@@ -11,24 +13,24 @@ use anyhow::{bail, Context, Result};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::io::Read;
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 
-#[cfg(feature = "neural")]
-use std::path::Path;
+use crate::config::embedding_client::{ClientConfig, EmbeddingClient};
+use crate::config::wizard::{
+    ApiProvider, AZURE_OPENAI_DEPLOYMENT_VAR, AZURE_OPENAI_ENDPOINT_VAR,
+    GOOGLE_APPLICATION_CREDENTIALS_VAR,
+};
 
 // Security constants for input validation
-const MAX_EMBEDDING_BATCH_SIZE: usize = 100; // Maximum texts per API request
-const MAX_TEXT_LENGTH: usize = 32_000; // Maximum characters per text (~8k tokens for most models)
 const MAX_DIMENSION: usize = 8192; // Maximum embedding dimension (larger than any known model)
 const MIN_DIMENSION: usize = 64; // Minimum reasonable embedding dimension
-const MAX_MODEL_NAME_LENGTH: usize = 256; // Maximum model name length
+pub(crate) const MAX_MODEL_NAME_LENGTH: usize = 256; // Maximum model name length
 const MAX_API_KEY_LENGTH: usize = 2048; // Maximum API key length
 const API_REQUEST_TIMEOUT_SECS: u64 = 120; // 2 minutes timeout for API requests
-const MAX_RESPONSE_SIZE_BYTES: usize = 100 * 1024 * 1024; // 100MB max response size
 
 /// Embedding model configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +53,9 @@ pub struct NeuralConfig {
     pub max_seq_length: usize,
     /// Batch size for bulk embedding
     pub batch_size: usize,
+    /// Directory for cached API embeddings; `None` disables the cache
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl Default for NeuralConfig {
@@ -65,6 +70,7 @@ impl Default for NeuralConfig {
             dimension: 1536,
             max_seq_length: 512,
             batch_size: 32,
+            cache_dir: None,
         }
     }
 }
@@ -209,6 +215,8 @@ pub mod onnx {
     pub struct OnnxEmbedder {
         session: Mutex<Session>,
         tokenizer: Tokenizer,
+        /// Whether the model takes `token_type_ids`, as BERT exports do
+        needs_token_types: bool,
         dimension: usize,
         max_seq_length: usize,
     }
@@ -220,20 +228,27 @@ pub mod onnx {
                 .with_optimization_level(GraphOptimizationLevel::Level3)?
                 .with_intra_threads(4)?
                 .commit_from_file(model_path)?;
+            let needs_token_types = session
+                .inputs
+                .iter()
+                .any(|input| input.name == "token_type_ids");
 
             let tokenizer = Tokenizer::from_file(tokenizer_path)
                 .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
 
-            // Detect dimension from model output shape
-            // Note: ort 2.0 removed tensor_dimensions(), use default dimension
-            let dimension: usize = 768;
-
-            Ok(Self {
+            let mut embedder = Self {
                 session: Mutex::new(session),
                 tokenizer,
-                dimension,
+                needs_token_types,
+                dimension: 0,
                 max_seq_length: 512,
-            })
+            };
+
+            // ort 2.0 removed tensor_dimensions(), so embed a probe and take
+            // the dimension from the output shape
+            embedder.dimension = embedder.embed("fn main() {}")?.len();
+
+            Ok(embedder)
         }
 
         /// Create from a pretrained model name (downloads if needed)
@@ -258,16 +273,16 @@ pub mod onnx {
             )
         }
 
-        fn mean_pool(&self, embeddings: &[f32], seq_len: usize) -> Vec<f32> {
-            let mut pooled = vec![0.0f32; self.dimension];
+        fn mean_pool(embeddings: &[f32], seq_len: usize, dimension: usize) -> Vec<f32> {
+            let mut pooled = vec![0.0f32; dimension];
 
             if seq_len == 0 {
                 return pooled;
             }
 
             for i in 0..seq_len {
-                for j in 0..self.dimension {
-                    pooled[j] += embeddings[i * self.dimension + j];
+                for j in 0..dimension {
+                    pooled[j] += embeddings[i * dimension + j];
                 }
             }
 
@@ -315,6 +330,7 @@ pub mod onnx {
                 Array2::from_shape_vec((1, seq_len), input_ids).context("Invalid input shape")?;
             let attention_mask_array = Array2::from_shape_vec((1, seq_len), attention_mask)
                 .context("Invalid mask shape")?;
+            let token_type_array = Array2::<i64>::zeros((1, seq_len));
 
             // Run inference - ort 2.0 takes owned view without reference
             let input_ids_tensor = TensorRef::from_array_view(input_ids_array.view())?;
@@ -326,10 +342,17 @@ pub mod onnx {
                 .lock()
                 .map_err(|e| anyhow::anyhow!("Failed to lock session: {}", e))?;
 
-            let outputs = session.run(ort::inputs![
+            let mut inputs = ort::inputs![
                 "input_ids" => input_ids_tensor,
                 "attention_mask" => attention_mask_tensor,
-            ])?;
+            ];
+            if self.needs_token_types {
+                inputs.push((
+                    "token_type_ids".into(),
+                    TensorRef::from_array_view(token_type_array.view())?.into(),
+                ));
+            }
+            let outputs = session.run(inputs)?;
 
             // Extract embeddings - ort 2.0 API
             // Try to get output by name first, then fallback to first
@@ -337,11 +360,12 @@ pub mod onnx {
                 .get("last_hidden_state")
                 .ok_or_else(|| anyhow::anyhow!("No output tensor found from ONNX model"))?;
 
-            // ort 2.0: try_extract_tensor returns (Shape, &[T])
-            let (_, data) = output.try_extract_tensor::<f32>()?;
-            let embeddings: Vec<f32> = data.to_vec();
+            // ort 2.0: try_extract_tensor returns (Shape, &[T]); the shape is
+            // [batch, sequence, hidden]
+            let (shape, data) = output.try_extract_tensor::<f32>()?;
+            let dimension = shape.last().map_or(0, |&d| d as usize);
 
-            Ok(self.mean_pool(&embeddings, seq_len))
+            Ok(Self::mean_pool(data, seq_len, dimension))
         }
 
         fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
@@ -356,85 +380,218 @@ pub mod onnx {
     }
 }
 
+// ============================================================================
+// Local model (offline fallback)
+// ============================================================================
+
+/// Env var pointing at a directory holding `model.onnx` and
+/// `tokenizer.json`, for embedding without a key or network access
+pub const EMBEDDING_LOCAL_MODEL_VAR: &str = "EMBEDDING_LOCAL_MODEL";
+/// Model looked for when no directory is configured
+pub const DEFAULT_LOCAL_MODEL: &str = "all-MiniLM-L6-v2";
+
+/// Directory of the local embedding model: [`EMBEDDING_LOCAL_MODEL_VAR`] if
+/// set, else `<cache dir>/narsil-mcp/models/all-MiniLM-L6-v2`
+pub fn local_model_dir() -> Option<PathBuf> {
+    std::env::var(EMBEDDING_LOCAL_MODEL_VAR)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(|dir| PathBuf::from(dir.trim()))
+        .or_else(|| {
+            directories::BaseDirs::new().map(|dirs| {
+                dirs.cache_dir()
+                    .join("narsil-mcp")
+                    .join("models")
+                    .join(DEFAULT_LOCAL_MODEL)
+            })
+        })
+}
+
+/// The model and tokenizer files in a local model directory, or an error
+/// saying how to get them
+pub fn local_model_files(dir: &Path) -> Result<(PathBuf, PathBuf)> {
+    let model = dir.join("model.onnx");
+    let tokenizer = dir.join("tokenizer.json");
+    if let Some(missing) = [&model, &tokenizer].into_iter().find(|f| !f.is_file()) {
+        bail!(
+            "Local embedding model file {} not found. Export it with:\n  \
+             optimum-cli export onnx --model sentence-transformers/{} {}",
+            missing.display(),
+            DEFAULT_LOCAL_MODEL,
+            dir.display()
+        );
+    }
+    Ok((model, tokenizer))
+}
+
+/// Whether an embedding API key or custom endpoint is set
+#[cfg(feature = "neural-onnx")]
+fn api_configured() -> bool {
+    [
+        "EMBEDDING_SERVER_ENDPOINT",
+        "EMBEDDING_API_KEY",
+        "VOYAGE_API_KEY",
+        "OPENAI_API_KEY",
+        "COHERE_API_KEY",
+        "GEMINI_API_KEY",
+        GOOGLE_APPLICATION_CREDENTIALS_VAR,
+        "AZURE_OPENAI_API_KEY",
+    ]
+    .iter()
+    .any(|name| std::env::var(name).is_ok())
+}
+
+/// The first Cohere, Gemini or Azure OpenAI key set, with its provider.
+/// A Gemini service-account path in `GOOGLE_APPLICATION_CREDENTIALS`
+/// counts as Gemini's key.
+fn other_api_key() -> Result<Option<(ApiProvider, String)>> {
+    let vars = [
+        (ApiProvider::Cohere, ApiProvider::Cohere.env_var_name()),
+        (ApiProvider::Gemini, ApiProvider::Gemini.env_var_name()),
+        (ApiProvider::Gemini, GOOGLE_APPLICATION_CREDENTIALS_VAR),
+        (
+            ApiProvider::AzureOpenAI,
+            ApiProvider::AzureOpenAI.env_var_name(),
+        ),
+    ];
+    for (provider, var) in vars {
+        if let Ok(key) = std::env::var(var) {
+            let key = crate::config::secrets::resolve_secret(&key)?;
+            if key.len() > MAX_API_KEY_LENGTH {
+                bail!(
+                    "API key length {} exceeds maximum of {}",
+                    key.len(),
+                    MAX_API_KEY_LENGTH
+                );
+            }
+            return Ok(Some((provider, key)));
+        }
+    }
+    Ok(None)
+}
+
 // ============================================================================
 // API Backend (Voyage, OpenAI, etc.)
 // ============================================================================
 
-/// API-based embedding provider (Voyage, OpenAI, etc.)
+/// API-based embedding provider (Voyage, OpenAI, Cohere, Gemini, etc.).
+///
+/// Requests go through the provider's [`EmbeddingClient`], so the same
+/// batching, truncation, rate limits and retries apply as in the wizard.
 pub struct ApiEmbedder {
-    client: reqwest::blocking::Client,
-    endpoint: String,
-    model: String,
-    api_key: Option<String>,
+    client: Box<dyn EmbeddingClient>,
+    provider: ApiProvider,
+    key: String,
+    config: ClientConfig,
     dimension: usize,
 }
 
 impl ApiEmbedder {
-    /// Create a reqwest client with security settings (timeout, limits)
-    fn create_secure_client() -> reqwest::blocking::Client {
-        reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(API_REQUEST_TIMEOUT_SECS))
-            .connect_timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client")
+    /// Client settings from the environment, with the longer timeout
+    /// indexing needs
+    ///
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` are honoured by reqwest
+    /// itself; `EMBEDDING_PROXY` overrides them. `EMBEDDING_TIMEOUT` replaces
+    /// the default request timeout.
+    fn client_config(model: &str) -> ClientConfig {
+        let config = ClientConfig::from_env();
+        ClientConfig {
+            model: Some(model.to_string()),
+            timeout: config
+                .timeout
+                .or(Some(Duration::from_secs(API_REQUEST_TIMEOUT_SECS))),
+            ..config
+        }
     }
 
-    /// Create a Voyage AI embedder
-    pub fn voyage(api_key: &str) -> Self {
+    /// Create an embedder for `provider`, authenticated with `key` (empty
+    /// for none) and expecting vectors of `dimension`
+    pub fn new(provider: ApiProvider, key: &str, config: ClientConfig, dimension: usize) -> Self {
+        let config = ClientConfig {
+            dimension: Some(dimension),
+            ..config
+        };
         Self {
-            client: Self::create_secure_client(),
-            endpoint: "https://api.voyageai.com/v1/embeddings".to_string(),
-            model: "voyage-code-2".to_string(),
-            api_key: Some(api_key.to_string()),
-            dimension: 1536,
+            client: provider.client(key, &config),
+            provider,
+            key: key.to_string(),
+            config,
+            dimension,
         }
     }
 
+    /// Create a Voyage AI embedder
+    pub fn voyage(api_key: &str) -> Self {
+        Self::voyage_with_model(api_key, "voyage-code-2")
+    }
+
     /// Create a Voyage AI embedder with custom model
     pub fn voyage_with_model(api_key: &str, model: &str) -> Self {
-        Self {
-            client: Self::create_secure_client(),
-            endpoint: "https://api.voyageai.com/v1/embeddings".to_string(),
-            model: model.to_string(),
-            api_key: Some(api_key.to_string()),
-            dimension: 1536,
-        }
+        Self::new(
+            ApiProvider::Voyage,
+            api_key,
+            Self::client_config(model),
+            1536,
+        )
     }
 
     /// Create an OpenAI embedder
     pub fn openai(api_key: &str) -> Self {
-        Self {
-            client: Self::create_secure_client(),
-            endpoint: "https://api.openai.com/v1/embeddings".to_string(),
-            model: "text-embedding-3-small".to_string(),
-            api_key: Some(api_key.to_string()),
-            dimension: 1536,
-        }
+        Self::openai_with_model(api_key, "text-embedding-3-small", 1536)
     }
 
     /// Create an OpenAI embedder with custom model
     pub fn openai_with_model(api_key: &str, model: &str, dimension: usize) -> Self {
-        Self {
-            client: Self::create_secure_client(),
-            endpoint: "https://api.openai.com/v1/embeddings".to_string(),
-            model: model.to_string(),
-            api_key: Some(api_key.to_string()),
+        Self::new(
+            ApiProvider::OpenAI,
+            api_key,
+            Self::client_config(model),
             dimension,
-        }
+        )
     }
 
     /// Create a custom API embedder
     pub fn custom(endpoint: &str, model: &str, api_key: Option<&str>, dimension: usize) -> Self {
-        Self {
-            client: Self::create_secure_client(),
-            endpoint: endpoint.to_string(),
-            model: model.to_string(),
-            api_key: api_key.map(|s| s.to_string()),
+        let config = ClientConfig {
+            endpoint: Some(endpoint.to_string()),
+            ..Self::client_config(model)
+        };
+        Self::new(
+            ApiProvider::Custom,
+            api_key.unwrap_or_default(),
+            config,
             dimension,
-        }
+        )
+    }
+
+    /// Send `headers` with every request
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.config.headers = headers;
+        self.client = self.provider.client(&self.key, &self.config);
+        self
     }
 }
 
+/// Run an embedding request to completion from the sync backend.
+///
+/// Indexing calls in from inside the server's runtime, where blocking on a
+/// future would panic, so the request gets a runtime of its own on a
+/// scoped thread.
+fn block_on<T: Send>(future: impl std::future::Future<Output = Result<T>> + Send) -> Result<T> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .context("Failed to start the embedding runtime")?
+                    .block_on(future)
+            })
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Embedding request panicked")))
+    })
+}
+
 impl EmbeddingBackend for ApiEmbedder {
     fn embed(&self, text: &str) -> Result<Vec<f32>> {
         let results = self.embed_batch(&[text.to_string()])?;
@@ -442,128 +599,120 @@ impl EmbeddingBackend for ApiEmbedder {
     }
 
     fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        // Input validation - batch size
+        // Input validation - empty batch
         if texts.is_empty() {
             bail!("Cannot embed empty batch");
         }
-        if texts.len() > MAX_EMBEDDING_BATCH_SIZE {
-            bail!(
-                "Batch size {} exceeds maximum of {}",
-                texts.len(),
-                MAX_EMBEDDING_BATCH_SIZE
-            );
-        }
 
-        // Input validation - text length
-        for (i, text) in texts.iter().enumerate() {
-            if text.len() > MAX_TEXT_LENGTH {
-                bail!(
-                    "Text at index {} is {} characters, exceeds maximum of {}",
-                    i,
-                    text.len(),
-                    MAX_TEXT_LENGTH
-                );
-            }
-        }
+        block_on(self.client.embed(texts))
+    }
 
-        #[derive(Serialize)]
-        struct Request<'a> {
-            model: &'a str,
-            input: &'a [String],
-        }
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
 
-        #[derive(Deserialize)]
-        struct Response {
-            data: Vec<EmbeddingData>,
-        }
+// ============================================================================
+// Embedding Cache
+// ============================================================================
 
-        #[derive(Deserialize)]
-        struct EmbeddingData {
-            embedding: Vec<f32>,
-        }
+/// Stores embeddings on disk so unchanged code isn't sent to the API again.
+///
+/// Entries are keyed by the SHA-256 of provider, model and input text, so
+/// switching either never returns vectors from another model.
+pub struct CachedEmbedder {
+    inner: Arc<dyn EmbeddingBackend>,
+    dir: PathBuf,
+    provider: String,
+    model: String,
+}
 
-        let mut request = self
-            .client
-            .post(&self.endpoint)
-            .header("Content-Type", "application/json")
-            .json(&Request {
-                model: &self.model,
-                input: texts,
-            });
+impl CachedEmbedder {
+    /// Cache `inner`'s embeddings in `dir`, which is created if needed
+    pub fn new(
+        inner: Arc<dyn EmbeddingBackend>,
+        dir: PathBuf,
+        provider: &str,
+        model: &str,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create embedding cache {}", dir.display()))?;
+        Ok(Self {
+            inner,
+            dir,
+            provider: provider.to_string(),
+            model: model.to_string(),
+        })
+    }
 
-        if let Some(key) = &self.api_key {
-            // Redact API key in logs - only show first/last 4 chars
-            let redacted = if key.len() > 8 {
-                format!("{}...{}", &key[..4], &key[key.len() - 4..])
-            } else {
-                "****".to_string()
-            };
-            tracing::debug!("Using API key: {}", redacted);
-            request = request.header("Authorization", format!("Bearer {}", key));
-        }
-
-        let mut resp = request.send().context("Failed to send embedding request")?;
-
-        let status = resp.status();
-
-        // Read response with size limit to prevent memory exhaustion
-        let mut limited_body = Vec::new();
-        let mut chunk_buffer = [0u8; 8192];
-        let mut total_read = 0;
-
-        loop {
-            match resp.read(&mut chunk_buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    total_read += n;
-                    if total_read > MAX_RESPONSE_SIZE_BYTES {
-                        bail!(
-                            "Response size exceeds maximum of {} bytes",
-                            MAX_RESPONSE_SIZE_BYTES
-                        );
-                    }
-                    limited_body.extend_from_slice(&chunk_buffer[..n]);
-                }
-                Err(e) => return Err(e).context("Failed to read response body"),
-            }
+    /// Cache file for `text`, spread over 256 subdirectories
+    fn entry_path(&self, text: &str) -> PathBuf {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        for part in [self.provider.as_str(), self.model.as_str(), text] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
         }
+        let hash = format!("{:x}", hasher.finalize());
+        self.dir.join(&hash[..2]).join(format!("{}.bin", hash))
+    }
 
-        let text = String::from_utf8(limited_body).context("Response body is not valid UTF-8")?;
+    /// A cached embedding; unreadable or mis-sized entries count as misses
+    fn read(&self, text: &str) -> Option<Vec<f32>> {
+        let bytes = std::fs::read(self.entry_path(text)).ok()?;
+        let embedding: Vec<f32> = bincode::deserialize(&bytes).ok()?;
+        (embedding.len() == self.inner.dimension()).then_some(embedding)
+    }
 
-        if !status.is_success() {
-            // Redact potential sensitive info from error messages
-            let safe_text = if text.len() > 500 {
-                format!("{}... (truncated)", &text[..500])
-            } else {
-                text.clone()
-            };
-            bail!("API error ({}): {}", status, safe_text);
-        }
+    /// Store an embedding, writing to a temp file first so a crash can't
+    /// leave a truncated entry
+    fn write(&self, text: &str, embedding: &[f32]) -> Result<()> {
+        let path = self.entry_path(text);
+        let parent = path.parent().context("Cache entry has no parent")?;
+        std::fs::create_dir_all(parent)?;
 
-        let response: Response = serde_json::from_str(&text).with_context(|| {
-            format!(
-                "Failed to parse embedding response: {}",
-                &text[..text.len().min(200)]
-            )
-        })?;
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&tmp, bincode::serialize(embedding)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+impl EmbeddingBackend for CachedEmbedder {
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let results = self.embed_batch(&[text.to_string()])?;
+        results.into_iter().next().context("No embedding returned")
+    }
 
-        // Validate response embedding dimensions
-        for (i, emb_data) in response.data.iter().enumerate() {
-            if emb_data.embedding.len() != self.dimension {
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut results: Vec<Option<Vec<f32>>> = texts.iter().map(|t| self.read(t)).collect();
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            let inputs: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let embeddings = self.inner.embed_batch(&inputs)?;
+            if embeddings.len() != inputs.len() {
                 bail!(
-                    "Embedding at index {} has dimension {}, expected {}",
-                    i,
-                    emb_data.embedding.len(),
-                    self.dimension
+                    "Expected {} embeddings, got {}",
+                    inputs.len(),
+                    embeddings.len()
                 );
             }
+
+            for (&i, embedding) in missing.iter().zip(embeddings) {
+                if let Err(e) = self.write(&texts[i], &embedding) {
+                    tracing::warn!("Failed to cache embedding: {}", e);
+                }
+                results[i] = Some(embedding);
+            }
         }
 
-        Ok(response.data.into_iter().map(|d| d.embedding).collect())
+        Ok(results.into_iter().flatten().collect())
     }
 
     fn dimension(&self) -> usize {
-        self.dimension
+        self.inner.dimension()
     }
 }
 
@@ -808,14 +957,24 @@ impl NeuralEngine {
     /// Create a new neural engine with API backend
     ///
     /// Supports custom embedding endpoints via `EMBEDDING_SERVER_ENDPOINT` environment variable.
-    /// If not set, falls back to Voyage or OpenAI based on model name.
+    /// If not set, falls back to Voyage or OpenAI based on model name, and
+    /// without either key to Cohere, Gemini or Azure OpenAI.
     ///
     /// # Environment Variables
     /// - `EMBEDDING_SERVER_ENDPOINT` (optional) - Custom embedding API endpoint URL
+    /// - `EMBEDDING_EXTRA_HEADERS` (optional) - Extra headers for the custom endpoint
     /// - `EMBEDDING_API_KEY` - Generic API key (checked first)
     /// - `VOYAGE_API_KEY` - Voyage AI specific API key
     /// - `OPENAI_API_KEY` - OpenAI specific API key
-    pub fn with_api(config: NeuralConfig) -> Result<Self> {
+    /// - `OPENAI_BASE_URL` (optional) - Base URL of an OpenAI-compatible API
+    /// - `COHERE_API_KEY` - Cohere API key
+    /// - `GEMINI_API_KEY` or `GOOGLE_APPLICATION_CREDENTIALS` - Gemini API key
+    ///   or service-account file
+    /// - `AZURE_OPENAI_API_KEY` - Azure OpenAI key, with `AZURE_OPENAI_ENDPOINT`
+    ///   and `AZURE_OPENAI_DEPLOYMENT`
+    ///
+    /// Key values of the form `keyring:narsil-mcp/<provider>` are read from the OS keyring.
+    pub fn with_api(mut config: NeuralConfig) -> Result<Self> {
         // Validate dimension bounds
         if config.dimension < MIN_DIMENSION || config.dimension > MAX_DIMENSION {
             bail!(
@@ -844,7 +1003,9 @@ impl NeuralEngine {
         let api_key = std::env::var("EMBEDDING_API_KEY")
             .or_else(|_| std::env::var("VOYAGE_API_KEY"))
             .or_else(|_| std::env::var("OPENAI_API_KEY"))
-            .ok();
+            .ok()
+            .map(|key| crate::config::secrets::resolve_secret(&key))
+            .transpose()?;
 
         // Validate API key length if present
         if let Some(ref key) = api_key {
@@ -857,7 +1018,10 @@ impl NeuralEngine {
             }
         }
 
-        let backend: Arc<dyn EmbeddingBackend>;
+        let api: ApiEmbedder;
+        // Which API and model the embeddings come from, for the cache key
+        let provider: String;
+        let model: String;
 
         // Check for custom endpoint first
         if let Ok(custom_endpoint) = std::env::var("EMBEDDING_SERVER_ENDPOINT") {
@@ -876,28 +1040,97 @@ impl NeuralEngine {
                 model_name
             );
 
-            backend = Arc::new(ApiEmbedder::custom(
+            let headers = crate::config::embedding_client::extra_headers_from_env()?;
+            api = ApiEmbedder::custom(
                 &validated_endpoint,
                 model_name,
                 api_key.as_deref(),
                 config.dimension,
-            ));
-        } else {
-            // Fallback to Voyage/OpenAI - API key is required
-            let api_key = api_key.context(
-                "No embedding API key found. Set EMBEDDING_API_KEY, VOYAGE_API_KEY, or OPENAI_API_KEY"
-            )?;
-
+            )
+            .with_headers(headers);
+            provider = validated_endpoint;
+            model = model_name.to_string();
+        } else if let Some(api_key) = api_key {
             let model_name = config.model_name.as_deref().unwrap_or("voyage-code-2");
-            backend = if model_name.contains("voyage") {
-                Arc::new(ApiEmbedder::voyage_with_model(&api_key, model_name))
-            } else {
-                Arc::new(ApiEmbedder::openai_with_model(
+            if model_name.contains("voyage") {
+                api = ApiEmbedder::new(
+                    ApiProvider::Voyage,
                     &api_key,
-                    model_name,
+                    ApiEmbedder::client_config(model_name),
                     config.dimension,
+                );
+                provider = crate::config::embedding_client::VOYAGE_EMBEDDINGS_URL.to_string();
+            } else if let Some(base_url) = std::env::var("OPENAI_BASE_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+            {
+                // OpenAI-compatible gateway (LiteLLM, OpenRouter, vLLM, ...)
+                let endpoint = validate_embedding_endpoint(&format!(
+                    "{}/embeddings",
+                    base_url.trim().trim_end_matches('/')
                 ))
-            };
+                .context("Invalid OPENAI_BASE_URL")?;
+                api = ApiEmbedder::custom(&endpoint, model_name, Some(&api_key), config.dimension);
+                provider = endpoint;
+            } else {
+                api = ApiEmbedder::openai_with_model(&api_key, model_name, config.dimension);
+                provider = format!(
+                    "{}/embeddings",
+                    crate::config::wizard::DEFAULT_OPENAI_BASE_URL
+                );
+            }
+            model = model_name.to_string();
+        } else {
+            // Keys the wizard writes for the other hosted providers
+            let (other, key) = other_api_key()?.context(
+                "No embedding API key found. Set EMBEDDING_API_KEY, VOYAGE_API_KEY, \
+                 OPENAI_API_KEY, COHERE_API_KEY, GEMINI_API_KEY or AZURE_OPENAI_API_KEY",
+            )?;
+            let mut client_config = ApiEmbedder::client_config(other.default_model());
+            if let Some(model_name) = &config.model_name {
+                client_config.model = Some(model_name.clone());
+            }
+            if other == ApiProvider::AzureOpenAI {
+                let endpoint = std::env::var(AZURE_OPENAI_ENDPOINT_VAR)
+                    .with_context(|| format!("Azure OpenAI needs {}", AZURE_OPENAI_ENDPOINT_VAR))?;
+                client_config.base_url = Some(
+                    validate_embedding_endpoint(endpoint.trim())
+                        .with_context(|| format!("Invalid {}", AZURE_OPENAI_ENDPOINT_VAR))?,
+                );
+                client_config.model =
+                    Some(std::env::var(AZURE_OPENAI_DEPLOYMENT_VAR).with_context(|| {
+                        format!("Azure OpenAI needs {}", AZURE_OPENAI_DEPLOYMENT_VAR)
+                    })?);
+            }
+            model = client_config.model.clone().unwrap_or_default();
+
+            // The configured dimension defaults to Voyage's; use the
+            // model's own unless one was given
+            if config.model_name.is_none()
+                && crate::config::embedding_client::dimension_from_env().is_none()
+            {
+                if let Some(dimension) = other.dimension(&model) {
+                    config.dimension = dimension;
+                }
+            }
+
+            tracing::info!(
+                "Using {} embeddings (model: {})",
+                other.display_name(),
+                model
+            );
+            api = ApiEmbedder::new(other, &key, client_config, config.dimension);
+            provider = other.id().to_string();
+        }
+
+        let mut backend: Arc<dyn EmbeddingBackend> = Arc::new(api);
+        if let Some(dir) = &config.cache_dir {
+            backend = Arc::new(CachedEmbedder::new(
+                backend,
+                dir.clone(),
+                &provider,
+                &model,
+            )?);
         }
 
         let store = SimpleVectorStore::new(config.dimension);
@@ -937,11 +1170,46 @@ impl NeuralEngine {
         })
     }
 
+    /// Create a new neural engine with the local model in
+    /// [`local_model_dir`], which needs no key or network (requires
+    /// neural-onnx feature)
+    #[cfg(feature = "neural-onnx")]
+    pub fn with_local(mut config: NeuralConfig) -> Result<Self> {
+        let dir = local_model_dir().context("No cache directory for the local model")?;
+        let (model_path, tokenizer_path) = local_model_files(&dir)?;
+        let embedder = onnx::OnnxEmbedder::new(&model_path, &tokenizer_path)?;
+        tracing::info!("Using local embedding model in {}", dir.display());
+
+        // The store must match the model, not the API default
+        config.dimension = embedder.dimension();
+        let store = SimpleVectorStore::new(config.dimension);
+
+        Ok(Self {
+            backend: Arc::new(embedder),
+            store,
+            documents: RwLock::new(HashMap::new()),
+            config,
+        })
+    }
+
     /// Create based on config
+    ///
+    /// With the neural-onnx feature, the "api" backend falls back to the
+    /// local model when no API key or endpoint is set.
     pub fn new(config: NeuralConfig) -> Result<Self> {
         match config.backend.as_str() {
             #[cfg(feature = "neural-onnx")]
             "onnx" => Self::with_onnx(config),
+            #[cfg(feature = "neural-onnx")]
+            "local" => Self::with_local(config),
+            #[cfg(feature = "neural-onnx")]
+            "api" if !api_configured() => Self::with_local(config).context(
+                "No embedding API key found (EMBEDDING_API_KEY, VOYAGE_API_KEY, \
+                 OPENAI_API_KEY, COHERE_API_KEY, GEMINI_API_KEY or AZURE_OPENAI_API_KEY) \
+                 and the local model could not be loaded",
+            ),
+            #[cfg(not(feature = "neural-onnx"))]
+            "local" => bail!("The local embedding backend requires the neural-onnx feature"),
             _ => Self::with_api(config),
         }
     }
@@ -972,18 +1240,21 @@ impl NeuralEngine {
         Ok(())
     }
 
-    /// Index multiple snippets in batch (with chunking to respect API limits)
+    /// Index multiple snippets in batch
+    ///
+    /// API backends split the batch into provider-sized requests themselves;
+    /// the request size can be lowered with `EMBEDDING_BATCH_SIZE`.
     pub fn index_batch(&self, items: &[(NeuralDocument,)]) -> Result<()> {
-        const BATCH_SIZE: usize = 96; // Voyage API limit is 128, use 96 for safety
+        if items.is_empty() {
+            return Ok(());
+        }
 
-        for chunk in items.chunks(BATCH_SIZE) {
-            let contents: Vec<String> = chunk.iter().map(|(doc,)| doc.content.clone()).collect();
-            let embeddings = self.backend.embed_batch(&contents)?;
+        let contents: Vec<String> = items.iter().map(|(doc,)| doc.content.clone()).collect();
+        let embeddings = self.backend.embed_batch(&contents)?;
 
-            for ((doc,), embedding) in chunk.iter().zip(embeddings.iter()) {
-                self.store.add(&doc.id, embedding);
-                self.documents.write().insert(doc.id.clone(), doc.clone());
-            }
+        for ((doc,), embedding) in items.iter().zip(embeddings.iter()) {
+            self.store.add(&doc.id, embedding);
+            self.documents.write().insert(doc.id.clone(), doc.clone());
         }
 
         Ok(())
@@ -1102,10 +1373,13 @@ mod tests {
             Some("test-key"),
             768,
         );
-        assert_eq!(custom.endpoint, "https://localhost:8080/v1/embeddings");
-        assert_eq!(custom.model, "custom-model");
+        assert_eq!(
+            custom.config.endpoint.as_deref(),
+            Some("https://localhost:8080/v1/embeddings")
+        );
+        assert_eq!(custom.config.model.as_deref(), Some("custom-model"));
         assert_eq!(custom.dimension, 768);
-        assert!(custom.api_key.is_some());
+        assert_eq!(custom.key, "test-key");
     }
 
     #[test]
@@ -1116,8 +1390,197 @@ mod tests {
             None,
             384,
         );
-        assert_eq!(custom.endpoint, "http://192.168.1.100:8080/embeddings");
-        assert!(custom.api_key.is_none());
+        assert_eq!(
+            custom.config.endpoint.as_deref(),
+            Some("http://192.168.1.100:8080/embeddings")
+        );
+        assert!(custom.key.is_empty());
+    }
+
+    /// Backend returning `[len, 1.0]` for each text, counting API calls
+    struct CountingBackend {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl EmbeddingBackend for CountingBackend {
+        fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(self.embed_batch(&[text.to_string()])?.remove(0))
+        }
+
+        fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+    }
+
+    fn counting_backend() -> Arc<CountingBackend> {
+        Arc::new(CountingBackend {
+            calls: std::sync::atomic::AtomicUsize::new(0),
+        })
+    }
+
+    #[test]
+    fn test_cached_embedder_reuses_embeddings() {
+        let temp = tempfile::tempdir().unwrap();
+        let inner = counting_backend();
+        let cached = CachedEmbedder::new(
+            inner.clone(),
+            temp.path().to_path_buf(),
+            "voyage",
+            "voyage-code-2",
+        )
+        .unwrap();
+
+        let first = cached.embed("fn main() {}").unwrap();
+        let second = cached.embed("fn main() {}").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Only the uncached input is sent, and order is kept
+        let batch = cached
+            .embed_batch(&["fn main() {}".to_string(), "struct A;".to_string()])
+            .unwrap();
+        assert_eq!(batch, vec![vec![12.0, 1.0], vec![9.0, 1.0]]);
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_cached_embedder_keys_on_provider_and_model() {
+        let temp = tempfile::tempdir().unwrap();
+        let inner = counting_backend();
+        let dir = temp.path().to_path_buf();
+
+        for (provider, model) in [
+            ("voyage", "voyage-code-2"),
+            ("voyage", "voyage-3"),
+            ("openai", "voyage-code-2"),
+        ] {
+            let cached = CachedEmbedder::new(inner.clone(), dir.clone(), provider, model).unwrap();
+            cached.embed("fn main() {}").unwrap();
+        }
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_cached_api_embedder_skips_http_call() {
+        let server = crate::config::mock_http::MockServer::start(vec![(
+            200,
+            r#"{"data":[{"embedding":[0.5,0.25]}]}"#,
+        )]);
+        let temp = tempfile::tempdir().unwrap();
+        let endpoint = format!("{}/v1/embeddings", server.url);
+        let api = ApiEmbedder::custom(&endpoint, "test-model", None, 2);
+        let cached = CachedEmbedder::new(
+            Arc::new(api),
+            temp.path().to_path_buf(),
+            &endpoint,
+            "test-model",
+        )
+        .unwrap();
+
+        assert_eq!(cached.embed("fn main() {}").unwrap(), vec![0.5, 0.25]);
+        assert_eq!(cached.embed("fn main() {}").unwrap(), vec![0.5, 0.25]);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_api_embedder_sends_extra_headers() {
+        let server = crate::config::mock_http::MockServer::start_requiring_header(
+            "X-Org-Id",
+            "acme",
+            vec![(200, r#"{"data":[{"embedding":[0.5,0.25]}]}"#)],
+        );
+        let endpoint = format!("{}/v1/embeddings", server.url);
+
+        let api = ApiEmbedder::custom(&endpoint, "test-model", Some("secret-key"), 2);
+        assert!(api.embed("fn main() {}").is_err());
+
+        let api = api.with_headers(vec![("X-Org-Id".to_string(), "acme".to_string())]);
+        assert_eq!(api.embed("fn main() {}").unwrap(), vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_api_embedder_uses_provider_client() {
+        let server = crate::config::mock_http::MockServer::start(vec![(
+            200,
+            r#"{"embeddings":[{"values":[0.5,0.25]}]}"#,
+        )]);
+        let config = ClientConfig {
+            base_url: Some(server.url.clone()),
+            ..ApiEmbedder::client_config("text-embedding-004")
+        };
+
+        let api = ApiEmbedder::new(ApiProvider::Gemini, "AIzaSyTest", config, 2);
+        assert_eq!(api.embed("fn main() {}").unwrap(), vec![0.5, 0.25]);
+        let requests = server.requests();
+        assert!(requests[0].starts_with("POST /models/text-embedding-004:batchEmbedContents "));
+    }
+
+    #[test]
+    fn test_api_embedder_keeps_to_rate_limit() {
+        let server = crate::config::mock_http::MockServer::start(vec![(
+            200,
+            r#"{"data":[{"embedding":[0.5,0.25]}]}"#,
+        )]);
+        // 60 requests a minute: a burst of 60, then one a second
+        let config = ClientConfig {
+            endpoint: Some(format!("{}/embeddings", server.url)),
+            batch_size: Some(1),
+            requests_per_minute: Some(60),
+            ..ApiEmbedder::client_config("test-model")
+        };
+        let api = ApiEmbedder::new(ApiProvider::Custom, "", config, 2);
+
+        let texts: Vec<String> = (0..62).map(|i| format!("fn f{}() {{}}", i)).collect();
+        let start = std::time::Instant::now();
+        assert_eq!(api.embed_batch(&texts).unwrap().len(), 62);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(1500));
+        assert_eq!(server.requests().len(), 62);
+    }
+
+    #[tokio::test]
+    async fn test_api_embedder_inside_runtime() {
+        // Indexing embeds from the server's async tasks
+        let server = crate::config::mock_http::MockServer::start(vec![(
+            200,
+            r#"{"data":[{"embedding":[0.5,0.25]}]}"#,
+        )]);
+        let endpoint = format!("{}/v1/embeddings", server.url);
+        let api = ApiEmbedder::custom(&endpoint, "test-model", None, 2);
+        assert_eq!(api.embed("fn main() {}").unwrap(), vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_local_model_files_missing() {
+        let temp = tempfile::tempdir().unwrap();
+        let err = local_model_files(temp.path()).unwrap_err().to_string();
+        assert!(err.contains("model.onnx"));
+        assert!(err.contains("optimum-cli"));
+
+        std::fs::write(temp.path().join("model.onnx"), b"").unwrap();
+        std::fs::write(temp.path().join("tokenizer.json"), b"{}").unwrap();
+        let (model, tokenizer) = local_model_files(temp.path()).unwrap();
+        assert_eq!(model, temp.path().join("model.onnx"));
+        assert_eq!(tokenizer, temp.path().join("tokenizer.json"));
+    }
+
+    #[cfg(feature = "neural-onnx")]
+    #[test]
+    #[ignore = "needs an exported all-MiniLM model in EMBEDDING_LOCAL_MODEL"]
+    fn test_local_model_embeds_offline() {
+        let dir = local_model_dir().unwrap();
+        let (model, tokenizer) = local_model_files(&dir).unwrap();
+        let embedder = onnx::OnnxEmbedder::new(&model, &tokenizer).unwrap();
+
+        let vector = embedder.embed("fn main() { println!(\"hi\"); }").unwrap();
+        assert_eq!(vector.len(), embedder.dimension());
+        assert_eq!(embedder.dimension(), 384);
+        let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-3);
     }
 
     mod custom_endpoint_integration {
@@ -1548,69 +2011,80 @@ mod tests {
             let result = embedder.embed_batch(&[]);
             assert!(result.is_err());
             assert!(result.unwrap_err().to_string().contains("empty batch"));
-
-            // Too large batch
-            let large_batch: Vec<String> = (0..200).map(|i| format!("text {}", i)).collect();
-            let result = embedder.embed_batch(&large_batch);
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("exceeds maximum"));
         }
 
         #[test]
-        fn test_text_length_validation() {
-            let embedder = ApiEmbedder::custom(
-                "https://api.example.com/embeddings",
-                "test-model",
-                Some("test-key"),
-                768,
-            );
-
-            // Text too long
-            let long_text = "a".repeat(40_000);
-            let result = embedder.embed_batch(&[long_text]);
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("exceeds maximum"));
+        fn test_large_batch_is_split_into_requests() {
+            let server = crate::config::mock_http::MockServer::start(vec![
+                (
+                    200,
+                    r#"{"data":[{"embedding":[1.0,0.0]},{"embedding":[2.0,0.0]}]}"#,
+                ),
+                (
+                    200,
+                    r#"{"data":[{"embedding":[3.0,0.0]},{"embedding":[4.0,0.0]}]}"#,
+                ),
+                (200, r#"{"data":[{"embedding":[5.0,0.0]}]}"#),
+            ]);
+            let config = ClientConfig {
+                endpoint: Some(format!("{}/embeddings", server.url)),
+                batch_size: Some(2),
+                ..ApiEmbedder::client_config("test-model")
+            };
+            let embedder = ApiEmbedder::new(ApiProvider::Custom, "", config, 2);
 
-            // Multiple texts, one too long
-            let texts = vec![
-                "normal text".to_string(),
-                "a".repeat(40_000),
-                "another normal text".to_string(),
-            ];
-            let result = embedder.embed_batch(&texts);
-            assert!(result.is_err());
-            assert!(result.unwrap_err().to_string().contains("index 1"));
+            let texts: Vec<String> = (0..5).map(|i| format!("text {}", i)).collect();
+            let vectors = embedder.embed_batch(&texts).unwrap();
+            let firsts: Vec<f32> = vectors.iter().map(|v| v[0]).collect();
+            assert_eq!(firsts, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+            assert_eq!(server.requests().len(), 3);
         }
 
         #[test]
-        fn test_valid_batch_sizes() {
-            let embedder = ApiEmbedder::custom(
-                "https://api.example.com/embeddings",
-                "test-model",
-                Some("test-key"),
-                768,
-            );
-
-            // Single text (valid size)
-            let texts = vec!["test text".to_string()];
-            // Note: This will fail at HTTP level (no actual server), but should pass validation
-            let _result = embedder.embed_batch(&texts);
+        fn test_long_text_is_truncated_when_indexed() {
+            let server = crate::config::mock_http::MockServer::start(vec![(
+                200,
+                r#"{"data":[{"embedding":[0.5,0.25]},{"embedding":[0.25,0.5]}]}"#,
+            )]);
+            let endpoint = format!("{}/embeddings", server.url);
+            let engine = NeuralEngine {
+                backend: Arc::new(ApiEmbedder::custom(&endpoint, "test-model", None, 2)),
+                store: SimpleVectorStore::new(2),
+                documents: RwLock::new(HashMap::new()),
+                config: NeuralConfig::default(),
+            };
 
-            // Maximum valid batch size
-            let max_batch: Vec<String> = (0..MAX_EMBEDDING_BATCH_SIZE)
-                .map(|i| format!("text {}", i))
-                .collect();
-            // Note: This will fail at HTTP level, but should pass validation
-            let _result = embedder.embed_batch(&max_batch);
+            // Unknown models are cut to the default token limit, not rejected
+            let doc = |id: &str, content: String| NeuralDocument {
+                id: id.to_string(),
+                file_path: "src/lib.rs".to_string(),
+                content,
+                start_line: 1,
+                end_line: 1,
+                symbol_name: None,
+            };
+            engine
+                .index_batch(&[
+                    (doc("long", "x".repeat(40_000)),),
+                    (doc("short", "fn main() {}".to_string()),),
+                ])
+                .unwrap();
+            assert_eq!(engine.documents.read().len(), 2);
+
+            let requests = server.requests();
+            let sent = requests[0].matches('x').count();
+            let limit = crate::config::embedding_client::DEFAULT_MAX_TOKENS
+                * crate::config::embedding_client::CHARS_PER_TOKEN;
+            assert!(sent <= limit, "{} characters sent", sent);
+            assert!(sent > limit / 2, "{} characters sent", sent);
         }
 
         #[test]
         fn test_http_client_has_timeout() {
-            // Create an embedder and verify the client has timeout configured
+            // Indexing gets a timeout, not the wizard's short default
             let embedder = ApiEmbedder::voyage("test-key");
-            // We can't directly inspect the client's timeout, but we can verify it was created
-            // In real usage, timeout would be tested by connecting to a slow server
-            assert!(embedder.api_key.is_some());
+            assert!(embedder.config.timeout.is_some());
+            assert_eq!(embedder.key, "test-key");
         }
     }
 }
@@ -1,5 +1,6 @@
 use narsil_mcp::config::editor::{
-    detect_available_editors, get_editor_config_path, EditorConfig, EditorType,
+    detect_available_editors, get_editor_config_path, get_editor_config_paths, EditorConfig,
+    EditorType,
 };
 use std::path::PathBuf;
 
@@ -29,6 +30,18 @@ fn test_claude_desktop_windows_path() {
     }
 }
 
+#[test]
+fn test_claude_desktop_linux_path() {
+    #[cfg(target_os = "linux")]
+    {
+        let path = get_editor_config_path(EditorType::ClaudeDesktop);
+        assert!(path.to_string_lossy().contains("Claude"));
+        assert!(path
+            .to_string_lossy()
+            .ends_with("Claude/claude_desktop_config.json"));
+    }
+}
+
 #[test]
 fn test_claude_code_path() {
     let path = get_editor_config_path(EditorType::ClaudeCode);
@@ -36,6 +49,20 @@ fn test_claude_code_path() {
     assert!(path.to_string_lossy().ends_with("claude_code_config.json"));
 }
 
+#[test]
+fn test_claude_code_project_path() {
+    let project = std::env::current_dir().unwrap().join(".mcp.json");
+    let paths = get_editor_config_paths(EditorType::ClaudeCode);
+    assert_eq!(paths.len(), 2);
+    assert!(paths.contains(&project));
+
+    // Without a project .mcp.json the user config stays the default
+    if !project.exists() {
+        assert_eq!(paths[1], project);
+        assert!(get_editor_config_path(EditorType::ClaudeCode).ends_with("claude_code_config.json"));
+    }
+}
+
 #[test]
 fn test_zed_macos_path() {
     #[cfg(target_os = "macos")]
@@ -56,13 +83,77 @@ fn test_zed_windows_path() {
     }
 }
 
+#[cfg(target_os = "linux")]
+#[test]
+fn test_zed_linux_path_honors_xdg_config_home() {
+    let temp = tempfile::tempdir().unwrap();
+    let previous = std::env::var_os("XDG_CONFIG_HOME");
+
+    std::env::set_var("XDG_CONFIG_HOME", temp.path());
+    let path = get_editor_config_path(EditorType::Zed);
+    let mcphub = get_editor_config_path(EditorType::Neovim);
+
+    // An empty value means unset, so the path falls back to ~/.config
+    std::env::set_var("XDG_CONFIG_HOME", "");
+    let fallback = get_editor_config_path(EditorType::Zed);
+
+    match previous {
+        Some(value) => std::env::set_var("XDG_CONFIG_HOME", value),
+        None => std::env::remove_var("XDG_CONFIG_HOME"),
+    }
+
+    assert_eq!(path, temp.path().join("zed").join("settings.json"));
+    assert!(fallback.ends_with(".config/zed/settings.json"));
+    assert_eq!(mcphub, temp.path().join("mcphub").join("servers.json"));
+}
+
 #[test]
 fn test_vscode_workspace_path() {
     let current_dir = std::env::current_dir().unwrap();
-    let path = get_editor_config_path(EditorType::VSCode);
+    let path = get_editor_config_paths(EditorType::VSCode)[0].clone();
     assert_eq!(path, current_dir.join(".vscode").join("mcp.json"));
 }
 
+#[test]
+fn test_vscode_candidate_order() {
+    let paths = get_editor_config_paths(EditorType::VSCode);
+    assert_eq!(paths.len(), 3);
+    assert!(paths[0].ends_with(".vscode/mcp.json"));
+    assert!(paths[1].ends_with("Code/User/mcp.json"));
+    assert!(paths[2].ends_with("Code - Insiders/User/mcp.json"));
+}
+
+#[test]
+fn test_zed_candidate_order() {
+    let paths = get_editor_config_paths(EditorType::Zed);
+    assert!(paths[0].ends_with("settings.json"));
+    assert!(paths[0].to_string_lossy().to_lowercase().contains("zed"));
+
+    #[cfg(target_os = "linux")]
+    {
+        assert_eq!(paths.len(), 3);
+        assert!(paths
+            .iter()
+            .any(|path| path.ends_with(".var/app/dev.zed.Zed/config/zed/settings.json")));
+    }
+}
+
+#[test]
+fn test_zed_project_path() {
+    let project = std::env::current_dir()
+        .unwrap()
+        .join(".zed")
+        .join("settings.json");
+    let paths = get_editor_config_paths(EditorType::Zed);
+    assert!(paths.contains(&project));
+
+    // Without project settings the user settings stay the default
+    if !project.exists() {
+        assert_eq!(paths.last(), Some(&project));
+        assert_ne!(get_editor_config_path(EditorType::Zed), project);
+    }
+}
+
 #[test]
 fn test_jetbrains_workspace_path() {
     let current_dir = std::env::current_dir().unwrap();
@@ -70,6 +161,54 @@ fn test_jetbrains_workspace_path() {
     assert_eq!(path, current_dir.join(".idea").join("mcp.json"));
 }
 
+#[test]
+fn test_cursor_path() {
+    let path = get_editor_config_path(EditorType::Cursor);
+    assert!(path.ends_with(".cursor/mcp.json"));
+
+    // Without a project config the global one in the home directory is used
+    let project = std::env::current_dir()
+        .unwrap()
+        .join(".cursor")
+        .join("mcp.json");
+    if !project.exists() {
+        assert_ne!(path, project);
+        assert!(path.starts_with(std::env::var_os("HOME").unwrap()));
+    }
+}
+
+#[test]
+fn test_windsurf_path() {
+    let path = get_editor_config_path(EditorType::Windsurf);
+    assert!(path.ends_with(".codeium/windsurf/mcp_config.json"));
+}
+
+#[test]
+fn test_continue_candidate_order() {
+    let paths = get_editor_config_paths(EditorType::Continue);
+    assert_eq!(paths.len(), 2);
+    assert!(paths[0].ends_with(".continue/config.yaml"));
+    assert!(paths[1].ends_with(".continue/config.json"));
+
+    let path = get_editor_config_path(EditorType::Continue);
+    assert!(paths.contains(&path));
+}
+
+#[test]
+fn test_sublime_text_path() {
+    let path = get_editor_config_path(EditorType::SublimeText);
+    assert!(path.ends_with("Packages/User/MCP.sublime-settings"));
+
+    #[cfg(target_os = "linux")]
+    assert!(path.to_string_lossy().contains("sublime-text"));
+}
+
+#[test]
+fn test_neovim_mcphub_path() {
+    let path = get_editor_config_path(EditorType::Neovim);
+    assert!(path.ends_with("mcphub/servers.json"));
+}
+
 #[test]
 fn test_editor_type_display() {
     assert_eq!(EditorType::ClaudeDesktop.to_string(), "Claude Desktop");
@@ -77,6 +216,11 @@ fn test_editor_type_display() {
     assert_eq!(EditorType::Zed.to_string(), "Zed");
     assert_eq!(EditorType::VSCode.to_string(), "VS Code");
     assert_eq!(EditorType::JetBrains.to_string(), "JetBrains IDEs");
+    assert_eq!(EditorType::Cursor.to_string(), "Cursor");
+    assert_eq!(EditorType::Windsurf.to_string(), "Windsurf");
+    assert_eq!(EditorType::Continue.to_string(), "Continue");
+    assert_eq!(EditorType::SublimeText.to_string(), "Sublime Text");
+    assert_eq!(EditorType::Neovim.to_string(), "Neovim");
 }
 
 #[test]
@@ -85,7 +229,7 @@ fn test_detect_available_editors_none() {
     let editors = detect_available_editors();
     // Should return empty or only editors with existing config files
     // This test will vary by environment
-    assert!(editors.len() <= 5);
+    assert!(editors.len() <= 10);
 }
 
 #[cfg(test)]